@@ -1,9 +1,9 @@
 use common::{
     anyhow::{self, format_err as err, Context},
-    glam::Vec3,
+    glam::{Quat, Vec3},
     hecs, log,
 };
-use components::{GLTFAsset, GLTFModel, Info, Material, Primitive, Texture, Vertex};
+use components::{GLTFAsset, GLTFModel, Info, Material, Primitive, Texture, Transform, Vertex};
 use gltf::Glb;
 use image::codecs::png::PngDecoder;
 use itertools::izip;
@@ -199,14 +199,19 @@ async fn load_and_insert(asset_name: String, sender: SyncSender<AssetResult>) {
         .unwrap_or_else(|e| log::error!("Failed to send asset: {e:?}"));
 }
 
-fn load(asset_name: String) -> anyhow::Result<GLTFModel> {
+/// Where to find one of the files in the assets folder.
+pub fn asset_path(asset_name: &str) -> String {
     #[cfg(debug_assertions)]
     let assets_folder = format!("{}/../assets", env!("CARGO_MANIFEST_DIR"));
 
     #[cfg(not(debug_assertions))]
     let assets_folder = "./assets";
 
-    let asset_path = format!("{assets_folder}/{asset_name}");
+    format!("{assets_folder}/{asset_name}")
+}
+
+fn load(asset_name: String) -> anyhow::Result<GLTFModel> {
+    let asset_path = asset_path(&asset_name);
     let file = std::fs::read(&asset_path).context(asset_path)?;
     let glb = Glb::from_slice(&file)?;
     let root = gltf::json::Root::from_slice(&glb.json)?;
@@ -238,6 +243,63 @@ fn load(asset_name: String) -> anyhow::Result<GLTFModel> {
     });
 }
 
+/// A node in a glTF scene, for when the scene describes where things go rather than what they
+/// look like.
+#[derive(Debug, Clone)]
+pub struct SceneNode {
+    pub name: String,
+    /// Where the node is in the world, taking its parents into account.
+    pub transform: Transform,
+    /// Whatever custom properties the node was given, or `Null` if it wasn't given any.
+    pub extras: serde_json::Value,
+}
+
+/// Reads every named node out of the default scene in `asset_name`, parents and children alike.
+/// Unlike models, scenes are loaded right away.
+pub fn load_scene(asset_name: &str) -> anyhow::Result<Vec<SceneNode>> {
+    let asset_path = asset_path(asset_name);
+    let file = std::fs::read(&asset_path).context(asset_path)?;
+    let gltf = gltf::Gltf::from_slice(&file)?;
+    let scene = gltf
+        .default_scene()
+        .or_else(|| gltf.scenes().next())
+        .ok_or_else(|| err!("No scenes found in glTF"))?;
+
+    let mut nodes = Vec::new();
+    for node in scene.nodes() {
+        import_node(&node, &Transform::default(), &mut nodes)?;
+    }
+    Ok(nodes)
+}
+
+fn import_node(
+    node: &gltf::Node<'_>,
+    parent: &Transform,
+    nodes: &mut Vec<SceneNode>,
+) -> anyhow::Result<()> {
+    let (translation, rotation, scale) = node.transform().decomposed();
+    let local = Transform::new(translation.into(), Quat::from_array(rotation), scale.into());
+    let transform = parent * &local;
+
+    if let Some(name) = node.name() {
+        let extras = match node.extras() {
+            Some(extras) => serde_json::from_str(extras.get())
+                .with_context(|| format!("Node {name} has extras that aren't valid JSON"))?,
+            None => serde_json::Value::Null,
+        };
+        nodes.push(SceneNode {
+            name: name.into(),
+            transform,
+            extras,
+        });
+    }
+
+    for child in node.children() {
+        import_node(&child, &transform, nodes)?;
+    }
+    Ok(())
+}
+
 fn import_vertices(primitive: &gltf::Primitive<'_>, blob: &[u8]) -> anyhow::Result<Vec<Vertex>> {
     let reader = primitive.reader(|_| Some(blob));
     let position_reader = reader
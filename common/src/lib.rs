@@ -103,6 +103,8 @@ impl Camera {
 #[derive(Debug, Clone, Default)]
 pub struct GUIState {
     pub game_over: bool,
+    /// How the game ended, for the game over screen.
+    pub outcome: String,
     pub paperclips: usize,
     pub idle_workers: usize,
     pub selected_item: Option<(Entity, SelectedItemInfo)>,
@@ -110,6 +112,15 @@ pub struct GUIState {
     pub bars: BarState,
     pub clock: String,
     pub clock_description: String,
+    pub is_daytime: bool,
+    /// The seed the map was generated from, so the player can share it.
+    pub seed: u64,
+    /// Recent news for the player, newest first.
+    pub notices: Vec<String>,
+    /// What the player has in the bank.
+    pub cash: i64,
+    /// What the player owes the bank.
+    pub loan: i64,
     pub total_deaths: usize,
 }
 
@@ -124,6 +135,7 @@ pub enum SelectedItemInfo {
     Viking(VikingInfo),
     PlaceOfWork(PlaceOfWorkInfo),
     Storage(StorageInfo),
+    Train(TrainInfo),
 }
 
 #[derive(Debug, Clone, Default)]
@@ -153,6 +165,19 @@ pub struct StorageInfo {
     pub stock: String,
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct TrainInfo {
+    pub id: u32,
+    pub name: String,
+    pub owner: u32,
+    pub speed: f32,
+    pub destination: String,
+    pub order: String,
+    pub cargo: String,
+    /// In kilograms, cargo included.
+    pub mass: f32,
+}
+
 pub trait Renderer {
     fn init(window: winit::window::Window) -> Self;
     fn unload_assets(&mut self);
@@ -188,6 +213,12 @@ pub enum GUICommand {
     Liquify(Entity),
     Restart,
     ConstructBuilding(&'static str), // this is awful
+    /// Buy a train at the station nearest the middle of the screen.
+    BuyTrain,
+    /// Build a station beside the track nearest the middle of the screen.
+    BuildStation,
+    Borrow(i64),
+    RepayLoan(i64),
 }
 
 pub const BUILDING_TYPE_MINE: &str = "mine";
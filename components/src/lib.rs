@@ -1,9 +1,10 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
 use common::{
     glam::{UVec2, Vec2, Vec3, Vec4},
     hecs::Entity,
 };
+use serde::Deserialize;
 mod transform;
 pub use transform::Transform;
 
@@ -112,7 +113,9 @@ pub struct MaterialOverrides {
     pub base_colour_factor: Vec4,
 }
 
-#[derive(Debug, Clone, enum_iterator::Sequence, Copy)]
+#[derive(
+    Debug, Clone, enum_iterator::Sequence, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize,
+)]
 pub enum Resource {
     Wood,
     Coal,
@@ -124,6 +127,55 @@ pub enum Resource {
     Crabs,
     Amethyst,
     GolfBalls,
+    Passengers,
+}
+
+/// A pile of resources, counted in whole units.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stockpile {
+    contents: BTreeMap<Resource, usize>,
+}
+
+impl Stockpile {
+    pub fn amount(&self, resource: Resource) -> usize {
+        self.contents.get(&resource).copied().unwrap_or(0)
+    }
+
+    pub fn total(&self) -> usize {
+        self.contents.values().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contents.is_empty()
+    }
+
+    pub fn add(&mut self, resource: Resource, amount: usize) {
+        if amount > 0 {
+            *self.contents.entry(resource).or_default() += amount;
+        }
+    }
+
+    /// Takes up to `amount` of `resource`, returning how much there actually was.
+    pub fn take(&mut self, resource: Resource, amount: usize) -> usize {
+        let Some(held) = self.contents.get_mut(&resource) else { return 0 };
+        let taken = amount.min(*held);
+        *held -= taken;
+        if *held == 0 {
+            self.contents.remove(&resource);
+        }
+        taken
+    }
+
+    /// Empties the stockpile, handing back everything that was in it.
+    pub fn take_all(&mut self) -> impl Iterator<Item = (Resource, usize)> {
+        std::mem::take(&mut self.contents).into_iter()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Resource, usize)> + '_ {
+        self.contents
+            .iter()
+            .map(|(resource, amount)| (*resource, *amount))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -142,3 +194,19 @@ pub struct Quota {
     pub resource: Resource,
     pub amount_per_day: usize,
 }
+
+/// Turns some resources into others. Recipes are loaded from `assets/recipes.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Recipe {
+    pub name: String,
+    pub inputs: Vec<Ingredient>,
+    pub outputs: Vec<Ingredient>,
+    /// How many times a day a business can work through the recipe, given enough inputs.
+    pub batches_per_day: u64,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Ingredient {
+    pub resource: Resource,
+    pub amount: usize,
+}
@@ -0,0 +1,221 @@
+// The in-game date and time. It's counted in simulation ticks rather than real time, so the
+// clock runs the same no matter how the frames fall.
+
+use crate::time::UPDATE_RATE;
+
+/// How long an in-game day lasts, in real seconds, unless we're told otherwise.
+pub const DEFAULT_DAY_LENGTH: f32 = 240.;
+const MINUTES_PER_DAY: u64 = 24 * 60;
+pub const DAYS_PER_SEASON: u64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    /// The hour the sun comes up. It goes down just as long before midnight.
+    fn sunrise(&self) -> f32 {
+        match self {
+            Season::Spring => 6.,
+            Season::Summer => 5.,
+            Season::Autumn => 7.,
+            Season::Winter => 8.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Calendar {
+    ticks: u64,
+    ticks_per_day: u64,
+}
+
+impl Default for Calendar {
+    fn default() -> Self {
+        Self::new(DEFAULT_DAY_LENGTH)
+    }
+}
+
+impl Calendar {
+    /// Starts the clock at six in the morning on the first day of spring, with each day lasting
+    /// `day_length` real seconds.
+    pub fn new(day_length: f32) -> Self {
+        let ticks_per_day = ((day_length / UPDATE_RATE).round() as u64).max(1);
+        Self {
+            ticks: ticks_per_day / 4,
+            ticks_per_day,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+    }
+
+    /// Ticks since the very start of the first day.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn ticks_per_day(&self) -> u64 {
+        self.ticks_per_day
+    }
+
+    /// How many days `ticks` ticks add up to.
+    pub fn days(&self, ticks: u64) -> f32 {
+        ticks as f32 / self.ticks_per_day as f32
+    }
+
+    /// Which day it is, starting from day 1.
+    pub fn day(&self) -> u64 {
+        self.ticks / self.ticks_per_day + 1
+    }
+
+    /// The season runs from spring to winter, then back round again.
+    pub fn season(&self) -> Season {
+        match ((self.day() - 1) / DAYS_PER_SEASON) % 4 {
+            0 => Season::Spring,
+            1 => Season::Summer,
+            2 => Season::Autumn,
+            _ => Season::Winter,
+        }
+    }
+
+    /// How far through the day we are, from 0 at midnight up to (but never reaching) 1.
+    pub fn time_of_day(&self) -> f32 {
+        (self.ticks % self.ticks_per_day) as f32 / self.ticks_per_day as f32
+    }
+
+    /// How much sunlight there is, from 0 between sunset and sunrise up to 1 at noon.
+    pub fn daylight(&self) -> f32 {
+        let sunrise = self.season().sunrise() / 24.;
+        let sunset = 1. - sunrise;
+        let time_of_day = self.time_of_day();
+        if time_of_day <= sunrise || time_of_day >= sunset {
+            return 0.;
+        }
+        let progress = (time_of_day - sunrise) / (sunset - sunrise);
+        (progress * std::f32::consts::PI).sin()
+    }
+
+    pub fn is_daytime(&self) -> bool {
+        self.daylight() > 0.
+    }
+
+    pub fn hour(&self) -> u32 {
+        self.minute_of_day() / 60
+    }
+
+    pub fn minute(&self) -> u32 {
+        self.minute_of_day() % 60
+    }
+
+    fn minute_of_day(&self) -> u32 {
+        ((self.ticks % self.ticks_per_day) * MINUTES_PER_DAY / self.ticks_per_day) as u32
+    }
+
+    /// The tick when the clock next reaches `hour:minute`.
+    pub fn next_time(&self, hour: u32, minute: u32) -> u64 {
+        let ticks_per_day = self.ticks_per_day;
+        let minute_of_day = (hour as u64 * 60 + minute as u64) % MINUTES_PER_DAY;
+        let offset = (minute_of_day * ticks_per_day + MINUTES_PER_DAY - 1) / MINUTES_PER_DAY;
+        let today = self.ticks - self.ticks % ticks_per_day + offset;
+        if today >= self.ticks {
+            today
+        } else {
+            today + ticks_per_day
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the clock on until it next reads `hour:minute`.
+    fn run_until(calendar: &mut Calendar, hour: u32, minute: u32) {
+        let until = calendar.next_time(hour, minute);
+        while calendar.ticks() < until {
+            calendar.tick();
+        }
+    }
+
+    /// The day, hour and minute.
+    fn clock(calendar: &Calendar) -> (u64, u32, u32) {
+        (calendar.day(), calendar.hour(), calendar.minute())
+    }
+
+    #[test]
+    fn a_day_lasts_as_long_as_its_told_to() {
+        let ticks_per_second = (1. / UPDATE_RATE).round() as u64;
+        let calendar = Calendar::new(DEFAULT_DAY_LENGTH);
+        assert_eq!(calendar.ticks_per_day(), 240 * ticks_per_second);
+        assert_eq!(Calendar::new(1.).ticks_per_day(), ticks_per_second);
+        assert_eq!(Calendar::new(0.).ticks_per_day(), 1);
+    }
+
+    #[test]
+    fn the_day_rolls_over_at_midnight() {
+        let mut calendar = Calendar::default();
+        assert_eq!(clock(&calendar), (1, 6, 0));
+
+        run_until(&mut calendar, 23, 59);
+        assert_eq!(calendar.day(), 1);
+        while calendar.day() == 1 {
+            calendar.tick();
+        }
+        assert_eq!(clock(&calendar), (2, 0, 0));
+        assert_eq!(calendar.time_of_day(), 0.);
+
+        for _ in 0..calendar.ticks_per_day() {
+            calendar.tick();
+        }
+        assert_eq!(clock(&calendar), (3, 0, 0));
+    }
+
+    #[test]
+    fn the_seasons_come_round_every_year() {
+        let mut calendar = Calendar::new(1.);
+        let mut seasons = Vec::new();
+        for _ in 0..DAYS_PER_SEASON * 5 {
+            seasons.push(calendar.season());
+            for _ in 0..calendar.ticks_per_day() {
+                calendar.tick();
+            }
+        }
+
+        let days = DAYS_PER_SEASON as usize;
+        let expected = [
+            Season::Spring,
+            Season::Summer,
+            Season::Autumn,
+            Season::Winter,
+            Season::Spring,
+        ];
+        for (chunk, season) in seasons.chunks(days).zip(expected) {
+            assert!(chunk.iter().all(|s| *s == season), "{chunk:?}");
+        }
+    }
+
+    #[test]
+    fn the_sun_is_only_up_during_the_day() {
+        let mut calendar = Calendar::new(1.);
+        for _ in 0..calendar.ticks_per_day() * DAYS_PER_SEASON * 4 {
+            let daylight = calendar.daylight();
+            assert!((0. ..=1.).contains(&daylight), "{daylight} at {calendar:?}");
+            if calendar.hour() < 5 || calendar.hour() >= 19 {
+                assert_eq!(daylight, 0., "at {calendar:?}");
+            }
+            calendar.tick();
+        }
+
+        run_until(&mut calendar, 0, 0);
+        assert!(!calendar.is_daytime());
+        run_until(&mut calendar, 12, 0);
+        assert!(calendar.is_daytime());
+        assert!((calendar.daylight() - 1.).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,59 @@
+use common::log;
+use std::collections::HashMap;
+
+/// Who something belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CompanyId(pub u32);
+
+/// The company being run by whoever's playing.
+pub const PLAYER_COMPANY: CompanyId = CompanyId(0);
+
+/// An amount of money, in whole currency units. Negative amounts are money going out.
+pub type Money = i64;
+
+/// One entry in the [`Ledger`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    pub company: CompanyId,
+    /// The in-game day it happened on.
+    pub day: u64,
+    pub amount: Money,
+    pub description: String,
+}
+
+/// Every bit of money that's changed hands, in the order it happened.
+#[derive(Debug, Clone, Default)]
+pub struct Ledger {
+    transactions: Vec<Transaction>,
+    /// Each company's transactions, added up as they're recorded.
+    balances: HashMap<CompanyId, Money>,
+}
+
+impl Ledger {
+    pub fn record<S: Into<String>>(
+        &mut self,
+        company: CompanyId,
+        day: u64,
+        amount: Money,
+        description: S,
+    ) {
+        let description = description.into();
+        log::info!("{company:?}: {amount:+} on day {day} for {description}");
+        *self.balances.entry(company).or_default() += amount;
+        self.transactions.push(Transaction {
+            company,
+            day,
+            amount,
+            description,
+        });
+    }
+
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+
+    /// How much a company has made (or lost) over all time.
+    pub fn balance(&self, company: CompanyId) -> Money {
+        self.balances.get(&company).copied().unwrap_or_default()
+    }
+}
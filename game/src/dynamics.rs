@@ -0,0 +1,108 @@
+// The physics of getting a train moving, and of stopping it again. Everything is in SI units:
+// kilograms, metres, seconds, newtons and watts.
+
+pub const GRAVITY: f32 = 9.81;
+
+/// Steel wheels on steel rails don't have much rolling resistance.
+const ROLLING_RESISTANCE_COEFFICIENT: f32 = 0.002;
+
+/// Half of air density * drag coefficient * frontal area, for a whole consist.
+const AIR_RESISTANCE_COEFFICIENT: f32 = 5.;
+
+/// Below this speed we treat the locomotive as stationary, so power limited tractive effort
+/// doesn't divide by zero.
+const MIN_POWER_SPEED: f32 = 0.1;
+
+#[derive(Debug, Clone)]
+pub struct Locomotive {
+    pub power: f32,
+    /// The most force the wheels can put down before they slip.
+    pub max_tractive_effort: f32,
+    pub braking_force: f32,
+    /// How fast the driver is willing to go.
+    pub max_speed: f32,
+}
+
+impl Default for Locomotive {
+    fn default() -> Self {
+        Self {
+            power: 100_000.,
+            max_tractive_effort: 60_000.,
+            braking_force: 60_000.,
+            max_speed: 3.,
+        }
+    }
+}
+
+impl Locomotive {
+    /// At low speeds we're limited by adhesion, at higher speeds by how much power we've got.
+    pub fn tractive_effort(&self, speed: f32) -> f32 {
+        self.max_tractive_effort
+            .min(self.power / speed.max(MIN_POWER_SPEED))
+    }
+}
+
+/// Rolling and air resistance, which always act against the direction of travel.
+pub fn running_resistance(mass: f32, speed: f32) -> f32 {
+    ROLLING_RESISTANCE_COEFFICIENT * mass * GRAVITY + AIR_RESISTANCE_COEFFICIENT * speed * speed
+}
+
+/// How hard gravity pulls `mass` back down a slope whose (normalised) direction of travel rises
+/// by `tangent_y`. Negative when heading downhill.
+pub fn gradient_resistance(mass: f32, tangent_y: f32) -> f32 {
+    mass * GRAVITY * tangent_y
+}
+
+/// How far we'd travel before stopping if we put the brakes on right now. `resistance` is
+/// everything else slowing us down, which may be negative on a downhill.
+pub fn braking_distance(speed: f32, mass: f32, braking_force: f32, resistance: f32) -> f32 {
+    let deceleration = (braking_force + resistance) / mass;
+    if deceleration <= 0. {
+        return f32::INFINITY;
+    }
+    speed * speed / (2. * deceleration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tractive_effort_is_limited_by_adhesion_then_power() {
+        let locomotive = Locomotive::default();
+        assert_eq!(
+            locomotive.tractive_effort(0.),
+            locomotive.max_tractive_effort
+        );
+        assert_eq!(
+            locomotive.tractive_effort(1.),
+            locomotive.max_tractive_effort
+        );
+
+        // Above power / adhesion the power runs out, and effort falls off with speed.
+        assert_eq!(locomotive.tractive_effort(10.), locomotive.power / 10.);
+        assert_eq!(locomotive.tractive_effort(20.), locomotive.power / 20.);
+    }
+
+    #[test]
+    fn gradients_pull_towards_the_bottom_of_the_hill() {
+        assert_eq!(gradient_resistance(1_000., 0.), 0.);
+        assert_eq!(gradient_resistance(1_000., 0.1), 1_000. * GRAVITY * 0.1);
+        assert_eq!(gradient_resistance(1_000., -0.1), -1_000. * GRAVITY * 0.1);
+    }
+
+    #[test]
+    fn braking_distance_depends_on_how_hard_we_can_slow_down() {
+        // 10 m/s at 1 m/s^2 takes 50 m to stop.
+        assert_eq!(braking_distance(10., 1_000., 1_000., 0.), 50.);
+        assert_eq!(braking_distance(10., 1_000., 500., 500.), 50.);
+        assert_eq!(braking_distance(0., 1_000., 1_000., 0.), 0.);
+
+        // Going downhill takes longer, and if the hill beats the brakes we're never stopping.
+        assert!(braking_distance(10., 1_000., 1_000., -500.) > 50.);
+        assert_eq!(
+            braking_distance(10., 1_000., 1_000., -1_000.),
+            f32::INFINITY
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use asset_loader::asset_path;
+use common::anyhow::{self, Context};
+use serde::Deserialize;
+
+const SETTINGS_FILE: &str = "generation.toml";
+
+/// Anything smaller doesn't leave room for the dry patch in the middle where the first track goes.
+const MIN_MAP_SIZE: f32 = 200.;
+/// Businesses go somewhere in a band this wide (in metres), out to `min_distance_to_resource`.
+pub const BUSINESS_BAND: f32 = 10.;
+
+/// Knobs for tuning how maps come out. Read from the assets folder, so they can be changed without
+/// recompiling; anything left out of the file keeps its default.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GenerationSettings {
+    /// Length of each side of the map, in metres.
+    pub map_size: f32,
+    /// Each resource gets fewer deposits than this.
+    pub max_resource_count: usize,
+    /// How far (in metres) each deposit's business is from it, give or take `BUSINESS_BAND`.
+    pub min_distance_to_resource: f32,
+    /// Businesses want at least `minimum_quota_amount` and fewer than `maximum_quota_amount` units
+    /// of each thing they need, each day.
+    pub minimum_quota_amount: usize,
+    pub maximum_quota_amount: usize,
+    /// Fewer than this many clumps of trees, each with fewer than this many trees.
+    pub max_clutter: usize,
+}
+
+impl Default for GenerationSettings {
+    fn default() -> Self {
+        Self {
+            map_size: 1000., // 1km squared
+            max_resource_count: 5,
+            min_distance_to_resource: 50.,
+            minimum_quota_amount: 10,
+            maximum_quota_amount: 50,
+            max_clutter: 50,
+        }
+    }
+}
+
+impl GenerationSettings {
+    /// Picks the `name=value` settings out of the command line, leaving flags and anything else
+    /// there to whoever they're meant for.
+    pub fn overrides_from_args<I: IntoIterator<Item = String>>(args: I) -> Vec<String> {
+        args.into_iter()
+            .filter(|arg| !arg.starts_with('-') && arg.contains('='))
+            .collect()
+    }
+
+    /// Reads the settings from the assets folder, then applies `overrides` on top. Each override
+    /// looks like `name=value`, as it would in the file.
+    pub fn load<S: AsRef<str>>(overrides: &[S]) -> anyhow::Result<Self> {
+        let path = asset_path(SETTINGS_FILE);
+        let file = std::fs::read_to_string(&path).context(path.clone())?;
+        Self::parse(&file, overrides).context(path)
+    }
+
+    pub fn parse<S: AsRef<str>>(file: &str, overrides: &[S]) -> anyhow::Result<Self> {
+        let mut table: toml::Table = toml::from_str(file)?;
+        for setting in overrides {
+            let setting = setting.as_ref();
+            let Some((name, value)) = setting.split_once('=') else {
+                anyhow::bail!("Expected a setting like `name=value`, but got `{setting}`");
+            };
+            let line = format!("{} = {}", name.trim(), value);
+            let overridden: toml::Table = toml::from_str(&line)
+                .with_context(|| format!("Couldn't understand the setting `{setting}`"))?;
+            table.extend(overridden);
+        }
+
+        let settings: Self = toml::Value::Table(table).try_into()?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Makes sure there's nothing here that'd stop a map being generated.
+    fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.map_size >= MIN_MAP_SIZE && self.map_size.is_finite(),
+            "map_size must be at least {MIN_MAP_SIZE}, but it's {}",
+            self.map_size
+        );
+        anyhow::ensure!(
+            self.max_resource_count > 0,
+            "max_resource_count must be more than 0"
+        );
+        anyhow::ensure!(
+            self.min_distance_to_resource >= BUSINESS_BAND
+                && self.min_distance_to_resource.is_finite(),
+            "min_distance_to_resource must be at least {BUSINESS_BAND}, but it's {}",
+            self.min_distance_to_resource
+        );
+        anyhow::ensure!(
+            self.minimum_quota_amount < self.maximum_quota_amount,
+            "minimum_quota_amount ({}) must be less than maximum_quota_amount ({})",
+            self.minimum_quota_amount,
+            self.maximum_quota_amount
+        );
+        anyhow::ensure!(
+            self.max_clutter > 5,
+            "max_clutter must be more than 5, but it's {}",
+            self.max_clutter
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_OVERRIDES: [&str; 0] = [];
+
+    #[test]
+    fn overrides_and_rejects_settings() {
+        let file = "map_size = 2000.0\nmax_clutter = 20";
+        let settings = GenerationSettings::parse(file, &["max_clutter = 10"]).unwrap();
+        assert_eq!(settings.map_size, 2000.);
+        assert_eq!(settings.max_clutter, 10);
+        assert_eq!(settings.maximum_quota_amount, 50);
+
+        assert!(GenerationSettings::parse(file, &["max_clutter=3"]).is_err());
+        assert!(GenerationSettings::parse(file, &["map_sise=3000.0"]).is_err());
+        assert!(GenerationSettings::parse(file, &["max_clutter"]).is_err());
+        assert!(GenerationSettings::parse("minimum_quota_amount = 60", &NO_OVERRIDES).is_err());
+        assert!(GenerationSettings::load(&NO_OVERRIDES).is_ok());
+    }
+
+    #[test]
+    fn only_settings_are_taken_from_the_command_line() {
+        let args = [
+            "--release",
+            "max_clutter=10",
+            "verbose",
+            "--level=3",
+            "map_size=2000",
+        ];
+        let overrides = GenerationSettings::overrides_from_args(args.map(String::from));
+        assert_eq!(overrides, ["max_clutter=10", "map_size=2000"]);
+    }
+}
@@ -1,56 +1,132 @@
+pub mod calendar;
+pub mod company;
+mod dynamics;
+mod generation_settings;
 mod input;
 mod map_generation;
+mod map_loader;
+mod placement;
+mod recipes;
+mod routing;
+pub mod scenario;
+mod spline;
+mod starter_network;
 mod systems;
+pub mod terrain;
+#[cfg(test)]
+mod test_helpers;
 pub mod time;
+use calendar::Calendar;
 use common::{
+    anyhow::{self, Context},
     bitflags::bitflags,
     glam::{Quat, Vec2, Vec3},
     hecs::{self, RefMut},
-    rapier3d::prelude::Ray,
+    log, rand,
+    rapier3d::prelude::{ColliderHandle, Ray},
     winit::{self},
-    Camera, GUIState, Line,
+    Camera, GUICommand, GUIState, Line, SelectedItemInfo, TrainInfo,
 };
-use components::{GLTFAsset, Transform, Velocity};
+use company::{CompanyId, Ledger, Money, PLAYER_COMPANY};
+use components::{Resource, Stockpile, Transform, Velocity};
+use generation_settings::GenerationSettings;
 use map_generation::generate_map;
-use std::time::Instant;
+use map_loader::load_map;
+use recipes::RecipeBook;
+use scenario::{scenario_system, Outcome, Scenario};
+use std::{
+    collections::{HashSet, VecDeque},
+    time::Instant,
+};
 use systems::{
+    contracts::{contracts_system, Contracts},
+    finances::{
+        charge, finances_system, FinanceError, Finances, LOCOMOTIVE_COST, STARTING_CASH,
+        STATION_COST, WAGON_COST,
+    },
     from_na,
-    train::{train_system, TrackSegment, Train},
+    market::{market_system, Market},
+    orders::{describe_order, Order, Orders},
+    physics,
+    production::{production_system, Deposit},
+    signalling::{signalling_system, Signalling},
+    station::{spawn_station, station_system, Consignment, Station, DEFAULT_CATCHMENT_RADIUS},
+    town::town_system,
+    track::{junction_switch_system, next_segment, Direction, Junction, TrackSegment},
+    track_builder::{track_builder_system, TrackBuilder},
+    train::{
+        consist_mass, despawn_train, spawn_train, train_focus_system, train_system, Cargo, Train,
+        TrainId, WAGON_CAPACITY,
+    },
     transform_hierarchy::transform_hierarchy_system,
     update_position::update_position_system,
     PhysicsContext,
 };
+use terrain::Terrain;
 use time::Time;
 
 pub const PLAYER_SPEED: f32 = 7.;
 pub const CAMERA_ZOOM_SPEED: f32 = 100.;
 pub const CAMERA_ROTATE_SPEED: f32 = 3.;
 pub const MAX_CAMERA_ZOOM: f32 = 400.;
-pub const MAP_SIZE: f32 = 1000.0; // 1km squared
 const RENDER_DEBUG_LINES: bool = false;
+/// How many notices the player can see at once. Older ones drop off the end.
+const MAX_NOTICES: usize = 5;
+/// How far from the middle of the screen, in metres, the player can buy trains and build stations.
+const BUILD_REACH: f32 = 20.;
 
 // required due to reasons
 #[no_mangle]
-pub fn init() -> Game {
-    Game::new()
+pub fn init() -> anyhow::Result<Game> {
+    // Map generation settings can be overridden on the command line, as `name=value`.
+    let overrides = GenerationSettings::overrides_from_args(std::env::args().skip(1));
+    let settings = GenerationSettings::load(&overrides)
+        .context("Unable to load the map generation settings")?;
+    Ok(Game::new(rand::random(), settings))
 }
 
 #[no_mangle]
-pub fn tick(game: &mut Game, _gui_state: &mut GUIState) -> bool {
+pub fn tick(game: &mut Game, gui_state: &mut GUIState) -> bool {
+    if handle_gui_commands(game, gui_state) {
+        log::info!("Starting a new game");
+        *game = Game::new(game.seed, game.generation_settings.clone());
+        update_gui_state(game, gui_state);
+        return true;
+    }
+
+    game.events.clear();
+
     while game.time.start_update() {
         game.debug_lines.clear();
         camera_target_controller(game);
         update_camera(game);
 
+        track_builder_system(game);
+        if !game.track_builder.active {
+            junction_switch_system(game);
+            train_focus_system(game);
+        }
+
         if !game.game_over {
+            game.calendar.tick();
+            production_system(game);
+            signalling_system(game);
             train_system(game);
+            station_system(game);
         }
 
         update_position_system(game);
+        physics(game);
         transform_hierarchy_system(game);
         reset_mouse_clicks(&mut game.input.mouse_state);
     }
 
+    if !game.game_over {
+        notices_system(game);
+        economy_system(game);
+        scenario_system(game);
+    }
+
     if let Some(last_ray) = game.last_ray {
         let origin = from_na(last_ray.origin);
         let direction: Vec3 = from_na(last_ray.dir);
@@ -67,9 +143,260 @@ pub fn tick(game: &mut Game, _gui_state: &mut GUIState) -> bool {
         game.debug_lines.clear();
     }
 
+    update_gui_state(game, gui_state);
+
     false
 }
 
+/// Settles up the day's business once a frame. The order matters: contracts are paid out against
+/// yesterday's quotas before towns set new ones, and prices follow the new demand.
+fn economy_system(game: &mut Game) {
+    contracts_system(game);
+    town_system(game);
+    market_system(game);
+    finances_system(game);
+}
+
+/// Carries out whatever the player asked for through the GUI, returning `true` if they want to
+/// start a new game. Once the game's over, that's all they can ask for.
+fn handle_gui_commands(game: &mut Game, gui_state: &mut GUIState) -> bool {
+    let mut restart = false;
+    for command in gui_state.command_queue.drain(..) {
+        let day = game.calendar.day();
+        match command {
+            GUICommand::Restart => restart = true,
+            command if game.game_over => log::debug!("Ignoring {command:?}, as the game's over"),
+            GUICommand::BuyTrain => buy_train_near_camera(game),
+            GUICommand::BuildStation => build_station_near_camera(game),
+            GUICommand::Borrow(amount) => {
+                let ledger = &mut game.ledger;
+                if let Err(e) = game.finances.borrow(ledger, PLAYER_COMPANY, day, amount) {
+                    game.notify(format!("Can't borrow {amount}: {e}"));
+                }
+            }
+            GUICommand::RepayLoan(amount) => {
+                let ledger = &mut game.ledger;
+                if let Err(e) = game.finances.repay(ledger, PLAYER_COMPANY, day, amount) {
+                    game.notify(format!("Can't repay {amount}: {e}"));
+                }
+            }
+            command => log::debug!("Ignoring {command:?}"),
+        }
+    }
+    restart
+}
+
+/// Buys the player a train at the station nearest the middle of the screen, to carry whatever's
+/// produced there to the next nearest station and back.
+fn buy_train_near_camera(game: &mut Game) {
+    let target = game.position_of(game.get_first_with_tag::<CameraTarget>());
+    let stations: Vec<(hecs::Entity, Vec3)> = game
+        .world
+        .query::<(&Station, &Transform)>()
+        .iter()
+        .map(|(entity, (_, transform))| (entity, transform.position))
+        .collect();
+    let nearest_to = |position: Vec3, except: Option<hecs::Entity>| {
+        stations
+            .iter()
+            .filter(|(entity, _)| Some(*entity) != except)
+            .map(|(entity, p)| (p.distance(position), *entity, *p))
+            .min_by(|(a, ..), (b, ..)| a.total_cmp(b))
+    };
+    let Some((distance, home, home_position)) = nearest_to(target, None) else {
+        game.notify("Can't buy a train without a station to put it at");
+        return;
+    };
+    if distance > BUILD_REACH {
+        game.notify("Can't buy a train, as there's no station nearby to put it at");
+        return;
+    }
+    let (home_name, segment, resource) = {
+        let station = game.world.get::<&Station>(home).unwrap();
+        let resource = station.catchment.iter().find_map(|entity| {
+            let mut query = game
+                .world
+                .query_one::<(&Resource, &Deposit)>(*entity)
+                .ok()?;
+            query.get().map(|(resource, _)| *resource)
+        });
+        (station.name.clone(), station.segment, resource)
+    };
+    let Some((_, away, _)) = nearest_to(home_position, Some(home)) else {
+        game.notify(format!("A train at {home_name} would have nowhere to go"));
+        return;
+    };
+    let Some(resource) = resource else {
+        game.notify(format!(
+            "There's nothing for a train to carry from {home_name}"
+        ));
+        return;
+    };
+
+    // The train starts off on the station's segment and runs on to the next one.
+    let next = next_segment(&game.world, segment, Direction::AToB).map(|(next, _)| next);
+    let in_the_way = game.world.query::<&Train>().iter().any(|(_, train)| {
+        train
+            .segments()
+            .any(|(occupied, _)| occupied == segment || Some(occupied) == next)
+    });
+    if in_the_way {
+        game.notify(format!("There's already a train at {home_name}"));
+        return;
+    }
+
+    let name = format!("Train {}", game.next_train_id + 1);
+    let wagons = vec![Cargo::new(vec![resource], WAGON_CAPACITY); 2];
+    let id = match game.buy_train(name.clone(), PLAYER_COMPANY, segment, wagons) {
+        Ok(id) => id,
+        Err(e) => {
+            game.notify(format!("Can't buy a train: {e}"));
+            return;
+        }
+    };
+    let entity = game.train_entity(id).unwrap();
+    game.world.get::<&mut Train>(entity).unwrap().orders = Orders::new(vec![
+        Order::GoTo(home),
+        Order::LoadUntilFull(resource),
+        Order::GoTo(away),
+        Order::Unload,
+    ]);
+    game.focused_train = Some(entity);
+    game.notify(format!("{name} is carrying {resource:?} from {home_name}"));
+}
+
+/// Builds the player a station beside the track nearest the middle of the screen, if there's
+/// any nearby that isn't a junction and hasn't got a station already.
+fn build_station_near_camera(game: &mut Game) {
+    let target = game.position_of(game.get_first_with_tag::<CameraTarget>());
+    let served: HashSet<hecs::Entity> = game
+        .world
+        .query::<&Station>()
+        .iter()
+        .map(|(_, station)| station.segment)
+        .collect();
+    let segment = game
+        .world
+        .query::<&TrackSegment>()
+        .iter()
+        .filter(|(entity, _)| !served.contains(entity))
+        .filter(|(entity, _)| game.world.get::<&Junction>(*entity).is_err())
+        .map(|(entity, segment)| (segment.curve.position(0.5).distance(target), entity))
+        .filter(|(distance, _)| *distance <= BUILD_REACH)
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, entity)| entity);
+    let Some(segment) = segment else {
+        game.notify("Can't build a station, as there's no free track nearby");
+        return;
+    };
+
+    let name = format!("Station {}", served.len() + 1);
+    match game.build_station(name.clone(), segment) {
+        Ok(_) => game.notify(format!("Opened {name}")),
+        Err(e) => game.notify(format!("Can't build a station: {e}")),
+    }
+}
+
+fn update_gui_state(game: &Game, gui_state: &mut GUIState) {
+    gui_state.game_over = game.game_over;
+    gui_state.outcome = match &game.outcome {
+        Some(Outcome::Won) => format!("You completed {}!", game.scenario.name),
+        Some(Outcome::Lost(reason)) => reason.clone(),
+        None => String::new(),
+    };
+
+    let calendar = &game.calendar;
+    gui_state.clock = format!(
+        "Day {}, {:02}:{:02}",
+        calendar.day(),
+        calendar.hour(),
+        calendar.minute()
+    );
+    gui_state.clock_description = format!("{:?}", calendar.season());
+    gui_state.is_daytime = calendar.is_daytime();
+    gui_state.seed = game.seed;
+    gui_state.notices = game.notices.iter().cloned().collect();
+    gui_state.cash = game.ledger.balance(PLAYER_COMPANY);
+    gui_state.loan = game.finances.loan(PLAYER_COMPANY);
+
+    gui_state.selected_item = game.focused_train.and_then(|entity| {
+        let train = game.world.get::<&Train>(entity).ok()?;
+        let speed = game.world.get::<&Velocity>(entity).ok()?.linear.length();
+        let info = TrainInfo {
+            id: train.id.0,
+            name: train.name.clone(),
+            owner: train.owner.0,
+            speed,
+            destination: match (train.dwell, train.destination) {
+                (Some(dwell), _) => match game.world.get::<&Station>(dwell.station) {
+                    Ok(station) => format!("Calling at {}", station.name),
+                    Err(_) => "Calling".into(),
+                },
+                (None, Some(destination)) => format!("{destination:?}"),
+                (None, None) => "Nowhere".into(),
+            },
+            cargo: describe_cargo(&game.world, &train),
+            mass: consist_mass(&game.world, &train),
+            order: match train.orders.current() {
+                Some(order) if train.orders.has_failed() => {
+                    format!("{} (stuck)", describe_order(&game.world, order))
+                }
+                Some(order) => describe_order(&game.world, order),
+                None => "None".into(),
+            },
+        };
+        Some((entity, SelectedItemInfo::Train(info)))
+    });
+}
+
+/// Tells the player about anything that's happened that they'll need to sort out themselves.
+fn notices_system(game: &mut Game) {
+    let mut notices = Vec::new();
+    let name_of = |train: hecs::Entity| {
+        let train = game.world.get::<&Train>(train).ok()?;
+        Some(train.name.clone())
+    };
+    for event in &game.events {
+        match event {
+            GameEvent::Deadlock(trains) => {
+                let names: Vec<String> = trains.iter().filter_map(|t| name_of(*t)).collect();
+                let names = names.join(" and ");
+                notices.push(format!("{names} are stuck waiting on each other"));
+            }
+            GameEvent::OrderFailed { train, reason } => {
+                let Some(name) = name_of(*train) else { continue };
+                notices.push(format!("{name} {reason}"));
+            }
+            GameEvent::Delivery { .. } => {}
+        }
+    }
+    for notice in notices {
+        game.notify(notice);
+    }
+}
+
+/// Adds up what's in all of the train's wagons, eg. "12 Wood, 3 Coal (15/60)".
+fn describe_cargo(world: &hecs::World, train: &Train) -> String {
+    let mut contents = Stockpile::default();
+    let mut capacity = 0;
+    for car in &train.cars {
+        let Ok(cargo) = world.get::<&Cargo>(*car) else { continue };
+        for (resource, amount) in cargo.contents().iter() {
+            contents.add(resource, amount);
+        }
+        capacity += cargo.capacity;
+    }
+
+    if contents.is_empty() {
+        return format!("Empty (0/{capacity})");
+    }
+    let items: Vec<String> = contents
+        .iter()
+        .map(|(resource, amount)| format!("{amount} {resource:?}"))
+        .collect();
+    format!("{} ({}/{capacity})", items.join(", "), contents.total())
+}
+
 #[no_mangle]
 pub fn handle_winit_event(game: &mut Game, event: winit::event::WindowEvent) {
     input::handle_winit_event(game, event);
@@ -78,7 +405,8 @@ pub fn handle_winit_event(game: &mut Game, event: winit::event::WindowEvent) {
 pub struct Game {
     pub world: hecs::World,
     pub time: Time,
-    pub train: hecs::Entity,
+    /// The train the camera is following and the UI is showing, if any.
+    pub focused_train: Option<hecs::Entity>,
     pub input: Input,
     pub camera: Camera,
     pub physics_context: PhysicsContext,
@@ -86,6 +414,49 @@ pub struct Game {
     pub debug_lines: Vec<Line>,
     pub last_ray: Option<Ray>,
     pub game_over: bool,
+    pub signalling: Signalling,
+    /// Anything interesting that happened since the last frame.
+    pub events: VecDeque<GameEvent>,
+    /// Recent news for the player, newest first.
+    pub notices: VecDeque<String>,
+    pub track_builder: TrackBuilder,
+    pub calendar: Calendar,
+    pub contracts: Contracts,
+    pub ledger: Ledger,
+    pub market: Market,
+    pub finances: Finances,
+    pub scenario: Scenario,
+    /// How the game ended, once it's over.
+    pub outcome: Option<Outcome>,
+    /// What the map was generated from. Starting a game with the same seed gives the same map.
+    pub seed: u64,
+    /// How the map was generated, which a restart sticks to.
+    pub generation_settings: GenerationSettings,
+    /// Everything businesses know how to make.
+    pub recipes: RecipeBook,
+    pub terrain: Terrain,
+    next_train_id: u32,
+}
+
+#[derive(Debug, Clone)]
+pub enum GameEvent {
+    /// These trains are all waiting on each other, and none of them will ever move again.
+    Deadlock(Vec<hecs::Entity>),
+    /// A train couldn't carry out its orders, and won't try again until the track changes.
+    OrderFailed {
+        train: hecs::Entity,
+        /// What went wrong, to follow the train's name.
+        reason: String,
+    },
+    /// A train dropped off some goods at a station.
+    Delivery {
+        train: hecs::Entity,
+        station: hecs::Entity,
+        resource: Resource,
+        amount: usize,
+        /// Where and when the goods were picked up, if we know.
+        consignment: Option<Consignment>,
+    },
 }
 
 impl Default for Game {
@@ -93,7 +464,7 @@ impl Default for Game {
         Self {
             world: Default::default(),
             time: Default::default(),
-            train: hecs::Entity::DANGLING,
+            focused_train: None,
             input: Default::default(),
             camera: Default::default(),
             physics_context: Default::default(),
@@ -101,33 +472,61 @@ impl Default for Game {
             debug_lines: Default::default(),
             last_ray: None,
             game_over: false,
+            signalling: Default::default(),
+            events: Default::default(),
+            notices: Default::default(),
+            track_builder: Default::default(),
+            calendar: Default::default(),
+            contracts: Default::default(),
+            ledger: Default::default(),
+            market: Default::default(),
+            finances: Default::default(),
+            scenario: Default::default(),
+            outcome: None,
+            seed: 0,
+            generation_settings: Default::default(),
+            recipes: Default::default(),
+            terrain: Default::default(),
+            next_train_id: 0,
         }
     }
 }
 
 impl Game {
-    pub fn new() -> Self {
+    pub fn new(seed: u64, generation_settings: GenerationSettings) -> Self {
         let mut world = hecs::World::default();
+        let recipes = RecipeBook::load().unwrap_or_else(|e| {
+            log::error!("Unable to load recipes, so businesses won't make anything: {e:?}");
+            Default::default()
+        });
+        let scenario = Scenario::load().unwrap_or_else(|e| {
+            log::error!("Unable to load the scenario, so there's nothing to win: {e:?}");
+            Default::default()
+        });
+        let map_size = generation_settings.map_size;
+        let loaded = scenario.map.as_ref().and_then(|map| {
+            log::info!("Loading the map {map}");
+            load_map(&mut world, &recipes, map, map_size)
+                .map_err(|e| {
+                    log::error!("Unable to load the map {map}, so generating one instead: {e:?}");
+                    world.clear();
+                })
+                .ok()
+        });
+        // Hand-built maps come with trains of their own. Generated ones get the starter network's.
+        let (terrain, trains) = match loaded {
+            Some(loaded) => loaded,
+            None => {
+                log::info!("Generating a map from seed {seed}");
+                let (terrain, network) =
+                    generate_map(&mut world, &recipes, &generation_settings, seed);
+                (terrain, network.trains())
+            }
+        };
+        let origin = terrain.surface(Vec3::ZERO);
         world.spawn((
-            GLTFAsset::new("map.glb"),
-            Transform {
-                scale: Vec3::splat(MAP_SIZE / 2.0),
-                ..Default::default()
-            },
-        ));
-        world.spawn((CameraTarget, Transform::default(), Velocity::default()));
-        let a = world.spawn((
-            GLTFAsset::new("tracks.glb"),
-            Transform::from_position([0., 0.1, 0.]),
-            TrackSegment { a: None, b: None },
-        ));
-        create_track_segments(&mut world, a, 10);
-        generate_map(&mut world);
-
-        let train = world.spawn((
-            Train { current_segment: a },
-            Transform::from_position([0., 0.4, 0.]),
-            GLTFAsset::new("train.glb"),
+            CameraTarget,
+            Transform::from_position(origin),
             Velocity::default(),
         ));
 
@@ -136,12 +535,128 @@ impl Game {
             ..Default::default()
         };
 
-        Game {
+        let mut game = Game {
             camera,
             world,
-            train,
+            recipes,
+            scenario,
+            seed,
+            generation_settings,
+            terrain,
             ..Default::default()
+        };
+        let day = game.calendar.day();
+        game.ledger
+            .record(PLAYER_COMPANY, day, STARTING_CASH, "starting capital");
+
+        for train in trains {
+            let id = game.spawn_train(train.name, PLAYER_COMPANY, train.segment, train.wagons);
+            let entity = game.train_entity(id).unwrap();
+            game.world.get::<&mut Train>(entity).unwrap().orders = Orders::new(train.orders);
+            game.focused_train.get_or_insert(entity);
+        }
+
+        game
+    }
+
+    /// Puts a new train on the track just past the `a` end of `start_segment`.
+    pub fn spawn_train<S: Into<String>>(
+        &mut self,
+        name: S,
+        owner: CompanyId,
+        start_segment: hecs::Entity,
+        wagons: Vec<Cargo>,
+    ) -> TrainId {
+        let id = TrainId(self.next_train_id);
+        self.next_train_id += 1;
+        spawn_train(
+            &mut self.world,
+            id,
+            name.into(),
+            owner,
+            start_segment,
+            wagons,
+        );
+        id
+    }
+
+    /// Buys a new train and puts it on the track, as long as `owner` can afford it.
+    pub fn buy_train<S: Into<String>>(
+        &mut self,
+        name: S,
+        owner: CompanyId,
+        start_segment: hecs::Entity,
+        wagons: Vec<Cargo>,
+    ) -> Result<TrainId, FinanceError> {
+        let name = name.into();
+        let cost = LOCOMOTIVE_COST + WAGON_COST * wagons.len() as Money;
+        let day = self.calendar.day();
+        charge(&mut self.ledger, owner, day, cost, format!("buying {name}"))?;
+        Ok(self.spawn_train(name, owner, start_segment, wagons))
+    }
+
+    /// Builds a station beside `segment` for the player, as long as they can afford it.
+    pub fn build_station<S: Into<String>>(
+        &mut self,
+        name: S,
+        segment: hecs::Entity,
+    ) -> Result<hecs::Entity, FinanceError> {
+        let name = name.into();
+        let day = self.calendar.day();
+        let description = format!("building {name}");
+        charge(
+            &mut self.ledger,
+            PLAYER_COMPANY,
+            day,
+            STATION_COST,
+            description,
+        )?;
+        Ok(spawn_station(
+            &mut self.world,
+            name,
+            segment,
+            DEFAULT_CATCHMENT_RADIUS,
+        ))
+    }
+
+    /// Puts a message up for the player.
+    pub fn notify<S: Into<String>>(&mut self, notice: S) {
+        self.notices.push_front(notice.into());
+        self.notices.truncate(MAX_NOTICES);
+    }
+
+    /// Stops the simulation, and lets the player know how it went.
+    pub fn end(&mut self, outcome: Outcome) {
+        log::info!("Game over: {outcome:?}");
+        self.game_over = true;
+        self.outcome = Some(outcome);
+    }
+
+    /// Takes a train, and all of its cars, out of the game. Returns `false` if there was no such
+    /// train.
+    pub fn despawn_train(&mut self, id: TrainId) -> bool {
+        let Some(entity) = self.train_entity(id) else { return false };
+
+        let cars = self.world.get::<&Train>(entity).unwrap().cars.clone();
+        for car in cars {
+            if let Ok(handle) = self.world.get::<&ColliderHandle>(car) {
+                self.physics_context.remove_collider(*handle);
+            }
         }
+        despawn_train(&mut self.world, entity);
+
+        if self.focused_train == Some(entity) {
+            self.focused_train = None;
+        }
+        true
+    }
+
+    pub fn train_entity(&self, id: TrainId) -> Option<hecs::Entity> {
+        self.world
+            .query::<&Train>()
+            .iter()
+            .find(|(_, train)| train.id == id)
+            .map(|(entity, _)| entity)
     }
 
     pub fn resized(&mut self, window_size: winit::dpi::PhysicalSize<u32>) {
@@ -179,26 +694,6 @@ impl Game {
     }
 }
 
-fn create_track_segments(world: &mut hecs::World, start: hecs::Entity, segments_remaining: usize) {
-    if segments_remaining == 0 {
-        return;
-    }
-
-    let x = world.get::<&mut Transform>(start).unwrap().position.x + 2.;
-    let y = world.get::<&mut Transform>(start).unwrap().position.y;
-    let a = world.spawn((
-        GLTFAsset::new("tracks.glb"),
-        Transform::from_position([x, y, 0.]),
-        TrackSegment {
-            a: Some(start),
-            b: None,
-        },
-    ));
-    world.get::<&mut TrackSegment>(start).unwrap().b = Some(a);
-
-    create_track_segments(world, a, segments_remaining - 1);
-}
-
 pub struct ECS<'a> {
     pub world: &'a hecs::World,
 }
@@ -313,6 +808,10 @@ pub fn update_camera(game: &mut Game) {
 }
 
 pub fn camera_target_controller(game: &mut Game) {
+    if follow_focused_train(game) {
+        return;
+    }
+
     let dt = game.time.delta();
     let camera_transform = game.camera.transform();
     let input = &game.input;
@@ -348,7 +847,30 @@ pub fn camera_target_controller(game: &mut Game) {
     // Velocity, baby!
     let displacement = velocity.linear * PLAYER_SPEED * (game.camera.desired_distance / 2.) * dt;
     transform.position += displacement;
-    transform.position.y = transform.position.y.min(5.).max(1.);
+    let (x, z) = (transform.position.x, transform.position.z);
+    let ground = game.terrain.height_at(x, z);
+    transform.position.y = transform.position.y.clamp(ground + 1., ground + 5.);
+}
+
+/// Keeps the camera on the focused train, until the player starts moving it themselves.
+fn follow_focused_train(game: &mut Game) -> bool {
+    let Some(train) = game.focused_train else { return false };
+    let moving = game
+        .input
+        .keyboard_state
+        .intersects(Keys::W | Keys::A | Keys::S | Keys::D);
+    let Ok(train_position) = game.world.get::<&Transform>(train).map(|t| t.position) else {
+        game.focused_train = None;
+        return false;
+    };
+    if moving {
+        game.focused_train = None;
+        return false;
+    }
+
+    let camera_target = game.get_first_with_tag::<CameraTarget>();
+    game.get::<Transform>(camera_target).position = train_position;
+    true
 }
 
 fn set_camera_distance(input: &Input, camera: &mut Camera, dt: f32) {
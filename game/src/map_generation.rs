@@ -1,12 +1,30 @@
 use common::{
     enum_iterator,
-    glam::Vec3,
+    glam::{Vec2, Vec3},
     hecs,
-    rand::{self, rngs::ThreadRng, Rng},
+    rand::{
+        rngs::StdRng,
+        seq::{IteratorRandom, SliceRandom},
+        Rng, SeedableRng,
+    },
+};
+use components::{
+    Business, Contract, GLTFAsset, MaterialOverrides, Quota, Recipe, Resource, Stockpile, Transform,
 };
-use components::{Business, Contract, GLTFAsset, MaterialOverrides, Quota, Resource, Transform};
 
-use crate::MAP_SIZE;
+use crate::{
+    generation_settings::{GenerationSettings, BUSINESS_BAND},
+    placement::{Footprint, Placement},
+    recipes::RecipeBook,
+    starter_network::{lay_starter_network, StarterNetwork},
+    systems::{
+        production::{Deposit, Workshop},
+        station::update_catchments,
+        town::{spawn_town, TOWN_RADIUS},
+        track::TrackSegment,
+    },
+    terrain::Terrain,
+};
 
 fn hex_to_rgb(hex: &str) -> Vec3 {
     let hex = hex.trim_start_matches("#");
@@ -30,49 +48,102 @@ pub fn get_resource_colour(r: Resource) -> Vec3 {
         Resource::Crabs => hex_to_rgb("#FF5722"),
         Resource::Amethyst => hex_to_rgb("#9C27B0"),
         Resource::GolfBalls => hex_to_rgb("#FFFFFF"),
+        Resource::Passengers => hex_to_rgb("#FFC107"),
     }
 }
 
-const MAX_RESOURCE_COUNT: usize = 5;
 const _MAX_BUSINESSES_PER_RESOURCE: usize = 5;
-const MIN_DISTANCE_TO_RESOURCE: f32 = 50.;
+/// How much room (in metres, from the middle) each kind of thing needs to itself.
+const DEPOSIT_RADIUS: f32 = 3.;
+const BUSINESS_RADIUS: f32 = 5.;
+const TREE_RADIUS: f32 = 1.5;
+/// How far from the middle of the starter network's track everything else has to keep, which
+/// leaves room for the platforms and signals alongside it.
+const TRACKSIDE_RADIUS: f32 = 2.5;
+/// How far trees spread out from the middle of their clump.
+const CLUTTER_SPREAD: f32 = 12.;
+const PLACEMENT_CELL_SIZE: f32 = 10.;
 const _MAX_DISTANCE_TO_RESOURCE: f32 = 200.;
-const MINIMUM_QUOTA_AMOUNT: usize = 10;
-const MAXIMUM_QUOTA_AMOUNT: usize = 50;
-const MAX_CLUTTER: usize = 50;
+const MIN_DEPOSIT_RATE: u64 = 10;
+const MAX_DEPOSIT_RATE: u64 = 60;
+const DEPOSIT_CAP: usize = 100;
+const WORKSHOP_CAP: usize = 100;
+const MIN_TOWNS: usize = 3;
+const MAX_TOWNS: usize = 6;
+const MIN_TOWN_POPULATION: u32 = 200;
+const MAX_TOWN_POPULATION: u32 = 2000;
+const TOWN_NAMES: [&str; 8] = [
+    "Ashford",
+    "Bramley",
+    "Coldwater",
+    "Dunmore",
+    "Eastleigh",
+    "Fernhill",
+    "Greystone",
+    "Hollowmere",
+];
+
+/// Raises the ground and lays the starter network, then fills the map with resources, businesses,
+/// towns and trees, all sat on dry land and none on top of each other or the network. The same
+/// seed and settings always give the same map.
+pub fn generate_map(
+    world: &mut hecs::World,
+    recipes: &RecipeBook,
+    settings: &GenerationSettings,
+    seed: u64,
+) -> (Terrain, StarterNetwork) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let map_size = settings.map_size;
+    let extent = map_size / 2.;
+    let terrain = Terrain::generate(seed, map_size);
+    world.spawn((
+        GLTFAsset::new(format!("terrain-{seed}")),
+        terrain.mesh(),
+        Transform::default(),
+    ));
+    world.spawn((
+        GLTFAsset::new("water"),
+        terrain.water_mesh(),
+        Transform::default(),
+    ));
+    let in_water = |position: Vec2| terrain.is_water(position.x, position.y);
+    let mut placement = Placement::new(map_size, PLACEMENT_CELL_SIZE, in_water);
+    let on_ground = |position: Vec2| terrain.surface(Vec3::new(position.x, 0., position.y));
 
-pub fn generate_map(world: &mut hecs::World) {
-    let mut rng = rand::thread_rng();
-    let extent = MAP_SIZE / 2.;
     // Some basic rules.
-    // 1. We have 10 resources that need to be on the map
+    // 1. The starter network goes exactly where it's meant to, so it's laid first and everything
+    // else keeps off it.
+    let network = lay_starter_network(world, &terrain);
+    for (position, radius) in starter_footprint(world, &network) {
+        placement.reserve(position, radius);
+    }
+
+    // 2. We have 10 resources that need to be on the map
     for resource in enum_iterator::all::<Resource>() {
-        for _ in 0..rng.gen_range(0..MAX_RESOURCE_COUNT) {
-            let x = rng.gen_range(-extent..extent);
-            let z = rng.gen_range(-extent..extent);
-            let resource_position = [x, 0., z].into();
-
-            world.spawn((
-                Transform {
-                    position: resource_position,
-                    scale: Vec3::splat(2.),
-                    ..Default::default()
-                },
-                GLTFAsset::new("cube.glb"),
-                resource,
-                MaterialOverrides {
-                    base_colour_factor: get_resource_colour(resource).extend(1.0),
-                },
-            ));
+        // Passengers come from towns, not out of the ground.
+        if resource == Resource::Passengers {
+            continue;
+        }
+        for _ in 0..rng.gen_range(0..settings.max_resource_count) {
+            let Some(resource_position) = placement.place_anywhere(&mut rng, DEPOSIT_RADIUS) else {
+                continue;
+            };
+
+            let rate = rng.gen_range(MIN_DEPOSIT_RATE..MAX_DEPOSIT_RATE);
+            spawn_deposit(world, resource, on_ground(resource_position), rate);
 
             // First, spawn a business that's *close* to this resource:
-            spawn_business(
-                world,
-                resource,
-                resource_position,
-                MIN_DISTANCE_TO_RESOURCE,
+            let business_position = placement.place_near(
                 &mut rng,
+                resource_position,
+                settings.min_distance_to_resource - BUSINESS_BAND,
+                settings.min_distance_to_resource,
+                BUSINESS_RADIUS,
             );
+            if let Some(business_position) = business_position {
+                let position = on_ground(business_position);
+                spawn_business_near(world, recipes, settings, resource, position, &mut rng);
+            }
 
             // Now spawn some businesses a little further away
             // for _ in 0..rng.gen_range(0..MAX_BUSINESSES_PER_RESOURCE) {
@@ -87,56 +158,257 @@ pub fn generate_map(world: &mut hecs::World) {
         }
     }
 
-    for _ in 0..rng.gen_range(5..MAX_CLUTTER) {
+    // 3. People have to live somewhere
+    let town_count = rng.gen_range(MIN_TOWNS..=MAX_TOWNS);
+    for name in TOWN_NAMES.choose_multiple(&mut rng, town_count) {
+        let population = rng.gen_range(MIN_TOWN_POPULATION..MAX_TOWN_POPULATION);
+        let Some(position) = placement.place_anywhere(&mut rng, TOWN_RADIUS) else { continue };
+        spawn_town(world, *name, on_ground(position), population);
+    }
+
+    // 4. Trees grow in clumps, in whatever room is left over.
+    for _ in 0..rng.gen_range(5..settings.max_clutter) {
         let x = rng.gen_range(-extent..extent);
         let z = rng.gen_range(-extent..extent);
 
-        for _ in 0..rng.gen_range(5..MAX_CLUTTER) {
-            let x_offset = rng.gen_range(-10.0..10.0);
-            let z_offset = rng.gen_range(-10.0..10.0);
-            let clutter_position = [x + x_offset, 0., z + z_offset].into();
-            world.spawn((
-                Transform {
-                    position: clutter_position,
-                    scale: Vec3::splat(1.),
-                    ..Default::default()
-                },
-                GLTFAsset::new("tree.glb"),
-            ));
+        for _ in 0..rng.gen_range(5..settings.max_clutter) {
+            let clutter_position =
+                placement.place_near(&mut rng, Vec2::new(x, z), 0., CLUTTER_SPREAD, TREE_RADIUS);
+            let Some(clutter_position) = clutter_position else { continue };
+            spawn_tree(world, on_ground(clutter_position));
         }
     }
+
+    // The stations were built before there was anything around them to serve.
+    update_catchments(world);
+    (terrain, network)
+}
+
+/// The ground the starter network takes up: a string of circles along all of the track (there's
+/// no other track yet), and one for the deposit.
+fn starter_footprint(world: &hecs::World, network: &StarterNetwork) -> Vec<(Vec2, f32)> {
+    let flatten = |position: Vec3| Vec2::new(position.x, position.z);
+    let mut footprint = Vec::new();
+    for (_, segment) in world.query::<&TrackSegment>().iter() {
+        // A metre or so apart, so they overlap.
+        let samples = segment.length().ceil().max(1.) as usize;
+        footprint.extend((0..=samples).map(|sample| {
+            let position = segment.curve.position(sample as f32 / samples as f32);
+            (flatten(position), TRACKSIDE_RADIUS)
+        }));
+    }
+    if let Ok(deposit) = world.get::<&Transform>(network.deposit) {
+        footprint.push((flatten(deposit.position), DEPOSIT_RADIUS));
+    }
+    footprint
+}
+
+pub fn spawn_tree(world: &mut hecs::World, position: Vec3) -> hecs::Entity {
+    world.spawn((
+        Transform {
+            position,
+            scale: Vec3::splat(1.),
+            ..Default::default()
+        },
+        GLTFAsset::new("tree.glb"),
+        Footprint::around(position, TREE_RADIUS),
+    ))
+}
+
+/// Puts a deposit of `resource` at `position` that produces `rate` units a day.
+pub fn spawn_deposit(
+    world: &mut hecs::World,
+    resource: Resource,
+    position: Vec3,
+    rate: u64,
+) -> hecs::Entity {
+    world.spawn((
+        Transform {
+            position,
+            scale: Vec3::splat(2.),
+            ..Default::default()
+        },
+        GLTFAsset::new("cube.glb"),
+        resource,
+        MaterialOverrides {
+            base_colour_factor: get_resource_colour(resource).extend(1.0),
+        },
+        Deposit::new(rate, DEPOSIT_CAP),
+        Stockpile::default(),
+        Footprint::around(position, DEPOSIT_RADIUS),
+    ))
 }
 
-fn spawn_business(
+/// Puts a business at `position`, near a deposit of `near_resource`. If there's a recipe that
+/// uses the resource, the business works to it, and wants each of the recipe's inputs.
+fn spawn_business_near(
     world: &mut hecs::World,
+    recipes: &RecipeBook,
+    settings: &GenerationSettings,
     near_resource: Resource,
-    resource_position: Vec3,
-    max_distance: f32,
-    rng: &mut ThreadRng,
+    position: Vec3,
+    rng: &mut StdRng,
 ) {
-    let distance: f32 = rng.gen_range(max_distance - 10.0..max_distance);
-    let angle: f32 = rng.gen_range(0.0..360.0);
-
-    let business_x = resource_position.x + distance * angle.to_radians().cos();
-    let business_z = resource_position.z + distance * angle.to_radians().sin();
-    let quota_amount = rng.gen_range(MINIMUM_QUOTA_AMOUNT..MAXIMUM_QUOTA_AMOUNT);
+    let recipe = recipes.using(near_resource).choose(rng).cloned();
+    let wants = match &recipe {
+        Some(recipe) => recipe.inputs.iter().map(|input| input.resource).collect(),
+        None => vec![near_resource],
+    };
+    let quotas = wants
+        .into_iter()
+        .map(|resource| Quota {
+            resource,
+            amount_per_day: rng
+                .gen_range(settings.minimum_quota_amount..settings.maximum_quota_amount),
+        })
+        .collect();
+    let name = recipe
+        .as_ref()
+        .map_or_else(|| "A Business".into(), |recipe| recipe.name.clone());
+    spawn_business(world, name, position, quotas, recipe);
+}
 
-    world.spawn((
+/// Puts a business called `name` at `position` that wants `quotas` delivered every day, and works
+/// to `recipe` if it has one.
+pub fn spawn_business(
+    world: &mut hecs::World,
+    name: String,
+    position: Vec3,
+    quotas: Vec<Quota>,
+    recipe: Option<Recipe>,
+) -> hecs::Entity {
+    let business = world.spawn((
         Transform {
-            position: [business_x, 0., business_z].into(),
+            position,
             scale: Vec3::splat(3.),
             ..Default::default()
         },
         GLTFAsset::new("building.glb"),
         Business {
-            name: "A Business".into(),
-            contract: Contract {
-                quotas: [Quota {
-                    resource: near_resource,
-                    amount_per_day: quota_amount,
-                }]
-                .into(),
-            },
+            name,
+            contract: Contract { quotas },
         },
+        Footprint::around(position, BUSINESS_RADIUS),
     ));
+    if let Some(recipe) = recipe {
+        world
+            .insert_one(business, Workshop::new(recipe, WORKSHOP_CAP))
+            .unwrap();
+    }
+    business
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::town::Town;
+
+    /// Everything about every entity that generation decides.
+    fn snapshot(world: &hecs::World) -> Vec<String> {
+        world
+            .iter()
+            .map(|entity| {
+                format!(
+                    "{:?} {:?} {:?} {:?} {:?}",
+                    entity.entity(),
+                    entity.get::<&Transform>().map(|t| *t),
+                    entity.get::<&Resource>().map(|r| *r),
+                    entity.get::<&Business>().map(|b| b.clone()),
+                    entity.get::<&GLTFAsset>().map(|a| a.clone()),
+                )
+            })
+            .collect()
+    }
+
+    fn generate(seed: u64) -> Vec<String> {
+        let recipes = RecipeBook::load().unwrap();
+        let mut world = hecs::World::new();
+        generate_map(&mut world, &recipes, &Default::default(), seed);
+        snapshot(&world)
+    }
+
+    #[test]
+    fn same_seed_same_map() {
+        let map = generate(1234);
+        assert!(!map.is_empty());
+        assert_eq!(map, generate(1234));
+        assert_ne!(map, generate(4321));
+    }
+
+    /// Where each thing generation put on the map is, how much room it's meant to have, and what
+    /// it is. The starter network is left out, as it goes where it's told.
+    fn footprints(world: &hecs::World, network: &StarterNetwork) -> Vec<(Vec2, f32, String)> {
+        world
+            .query::<(&Transform, &GLTFAsset)>()
+            .iter()
+            .filter(|(entity, _)| *entity != network.deposit)
+            .filter_map(|(entity, (transform, asset))| {
+                let radius = if world.get::<&Town>(entity).is_ok() {
+                    TOWN_RADIUS
+                } else if world.get::<&Business>(entity).is_ok() {
+                    BUSINESS_RADIUS
+                } else if world.get::<&Deposit>(entity).is_ok() {
+                    DEPOSIT_RADIUS
+                } else if asset.name == "tree.glb" {
+                    TREE_RADIUS
+                } else {
+                    // The ground, the water, and the rest of the starter network.
+                    return None;
+                };
+                let position = Vec2::new(transform.position.x, transform.position.z);
+                Some((position, radius, asset.name.clone()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn everything_has_room_and_stays_on_the_map() {
+        let recipes = RecipeBook::load().unwrap();
+        let settings = GenerationSettings::default();
+        for seed in 0..20 {
+            let mut world = hecs::World::new();
+            let (terrain, network) = generate_map(&mut world, &recipes, &settings, seed);
+            let placed = footprints(&world, &network);
+            assert!(placed.iter().any(|(_, _, name)| name == "building.glb"));
+
+            for (i, (position, radius, name)) in placed.iter().enumerate() {
+                assert!(
+                    position.abs().max_element() + radius <= settings.map_size / 2.,
+                    "seed {seed}: {name} at {position} is off the map"
+                );
+                assert!(
+                    !terrain.is_water(position.x, position.y),
+                    "seed {seed}: {name} at {position} is under water"
+                );
+                for (other_position, other_radius, other_name) in &placed[i + 1..] {
+                    assert!(
+                        position.distance(*other_position) >= radius + other_radius,
+                        "seed {seed}: {name} at {position} overlaps {other_name} at \
+                         {other_position}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn nothing_is_put_on_the_starter_network() {
+        let recipes = RecipeBook::load().unwrap();
+        let settings = GenerationSettings::default();
+        for seed in 0..20 {
+            let mut world = hecs::World::new();
+            let (_, network) = generate_map(&mut world, &recipes, &settings, seed);
+            let reserved = starter_footprint(&world, &network);
+            assert!(reserved.len() > 20);
+
+            for (position, radius, name) in footprints(&world, &network) {
+                for (reserved_position, reserved_radius) in &reserved {
+                    assert!(
+                        position.distance(*reserved_position) >= radius + reserved_radius,
+                        "seed {seed}: {name} at {position} is on the starter network"
+                    );
+                }
+            }
+        }
+    }
 }
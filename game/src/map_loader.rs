@@ -0,0 +1,406 @@
+// Hand-built maps, made in Blender (or anything else that exports glTF) and played on instead of a
+// generated one. Each named node in the scene is something to put in the world. Its name says
+// what it is, and its custom properties (glTF calls them extras) fill in the details:
+//
+// - `tree`
+// - `resource:Coal` (or any other resource), with an optional daily `rate`
+// - `business`, with a `name`, and a `recipe` to work to or a comma separated list of resources it
+//   `wants`, or both, and an optional `quota` of each it wants a day
+// - `town`, with a `name` and an optional `population`
+// - `track`, a point on the `line` it's named for. Points are joined up in order of their `index`,
+//   and a point with a `station` name gets a station on the track leaving it
+// - `train`, with a `name`, the `station` it starts at, an optional number of `wagons`, and a
+//   comma separated list of `orders` to work through: a station to go to, `load` and a resource,
+//   `unload`, or `wait` and a time like `08:00`. Its wagons carry whatever it's told to load
+//
+// Blender tacks `.001` and so on onto the names of copies, so that part of a name is ignored, and
+// so is any node with a name we don't know, like cameras and lights. Everything's put down on flat
+// ground wherever its node is, apart from trains, which go wherever their station is.
+
+use crate::{
+    map_generation::{spawn_business, spawn_deposit, spawn_tree},
+    recipes::RecipeBook,
+    spline::CubicBezier,
+    systems::{
+        orders::Order,
+        station::{spawn_station, Station, DEFAULT_CATCHMENT_RADIUS},
+        town::spawn_town,
+        track::{connect_segments, spawn_track_segment, TRACK_CLEARANCE},
+        train::{Cargo, StartingTrain, WAGON_CAPACITY},
+    },
+    terrain::Terrain,
+};
+use asset_loader::SceneNode;
+use common::{
+    anyhow::{self, Context},
+    glam::Vec3,
+    hecs::{self, Entity},
+    log,
+};
+use components::{GLTFAsset, Quota, Resource, Transform};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::collections::BTreeMap;
+
+const DEFAULT_DEPOSIT_RATE: u64 = 30;
+const DEFAULT_QUOTA: usize = 20;
+const DEFAULT_POPULATION: u32 = 500;
+const DEFAULT_WAGONS: usize = 2;
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DepositProperties {
+    rate: u64,
+}
+
+impl Default for DepositProperties {
+    fn default() -> Self {
+        Self {
+            rate: DEFAULT_DEPOSIT_RATE,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BusinessProperties {
+    name: String,
+    recipe: Option<String>,
+    #[serde(default)]
+    wants: String,
+    #[serde(default = "default_quota")]
+    quota: usize,
+}
+
+fn default_quota() -> usize {
+    DEFAULT_QUOTA
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TownProperties {
+    name: String,
+    #[serde(default = "default_population")]
+    population: u32,
+}
+
+fn default_population() -> u32 {
+    DEFAULT_POPULATION
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TrackProperties {
+    line: String,
+    index: i64,
+    station: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TrainProperties {
+    name: String,
+    station: String,
+    #[serde(default)]
+    orders: String,
+    #[serde(default = "default_wagons")]
+    wagons: usize,
+}
+
+fn default_wagons() -> usize {
+    DEFAULT_WAGONS
+}
+
+struct TrackPoint {
+    index: i64,
+    position: Vec3,
+    station: Option<String>,
+}
+
+/// Fills the world with everything in the glTF scene `file_name` from the assets folder, on a
+/// `size` metre square of flat ground, and hands back the trains that are to run on it.
+pub fn load_map(
+    world: &mut hecs::World,
+    recipes: &RecipeBook,
+    file_name: &str,
+    size: f32,
+) -> anyhow::Result<(Terrain, Vec<StartingTrain>)> {
+    let nodes = asset_loader::load_scene(file_name)?;
+    let terrain = Terrain::flat(size);
+    world.spawn((
+        GLTFAsset::new(format!("ground-{file_name}")),
+        terrain.mesh(),
+        Transform::default(),
+    ));
+
+    let mut lines: BTreeMap<String, Vec<TrackPoint>> = BTreeMap::new();
+    let mut trains = Vec::new();
+    for node in &nodes {
+        let position = terrain.surface(node.transform.position);
+        let kind = node.name.split('.').next().unwrap_or_default();
+        match kind.split_once(':') {
+            Some(("resource", resource)) => {
+                let resource = parse_resource(resource)?;
+                let properties: DepositProperties = read_properties(node)?;
+                spawn_deposit(world, resource, position, properties.rate);
+            }
+            None if kind == "tree" => {
+                spawn_tree(world, position);
+            }
+            None if kind == "business" => {
+                let properties: BusinessProperties = read_properties(node)?;
+                spawn_business_from(world, recipes, properties, position)
+                    .with_context(|| format!("Couldn't set up {}", node.name))?;
+            }
+            None if kind == "town" => {
+                let properties: TownProperties = read_properties(node)?;
+                spawn_town(world, properties.name, position, properties.population);
+            }
+            None if kind == "track" => {
+                let properties: TrackProperties = read_properties(node)?;
+                lines.entry(properties.line).or_default().push(TrackPoint {
+                    index: properties.index,
+                    position: position + Vec3::Y * TRACK_CLEARANCE,
+                    station: properties.station,
+                });
+            }
+            None if kind == "train" => {
+                trains.push(read_properties::<TrainProperties>(node)?);
+            }
+            _ => log::debug!("Leaving out {}, as it isn't anything we know", node.name),
+        }
+    }
+
+    // Trains can only be put on the track once it's all been laid.
+    let mut stations = BTreeMap::new();
+    for (line, points) in lines {
+        lay_line(world, points, &mut stations)
+            .with_context(|| format!("Couldn't lay the {line} line"))?;
+    }
+    let trains = trains
+        .into_iter()
+        .map(|properties| {
+            let name = properties.name.clone();
+            starting_train(world, &stations, properties)
+                .with_context(|| format!("Couldn't set up {name}"))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok((terrain, trains))
+}
+
+/// Reads a node's custom properties into `T`.
+fn read_properties<T: DeserializeOwned>(node: &SceneNode) -> anyhow::Result<T> {
+    let extras = match &node.extras {
+        serde_json::Value::Null => serde_json::Value::Object(Default::default()),
+        extras => extras.clone(),
+    };
+    serde_json::from_value(extras)
+        .with_context(|| format!("{} has the wrong custom properties", node.name))
+}
+
+fn parse_resource(name: &str) -> anyhow::Result<Resource> {
+    let name = name.trim();
+    serde_json::from_value(serde_json::Value::String(name.into()))
+        .with_context(|| format!("There's no such resource as {name}"))
+}
+
+fn spawn_business_from(
+    world: &mut hecs::World,
+    recipes: &RecipeBook,
+    properties: BusinessProperties,
+    position: Vec3,
+) -> anyhow::Result<hecs::Entity> {
+    let recipe = match &properties.recipe {
+        Some(name) => {
+            let recipe = recipes.named(name);
+            Some(recipe.with_context(|| format!("There's no recipe called {name}"))?)
+        }
+        None => None,
+    };
+
+    let mut wants = Vec::new();
+    if let Some(recipe) = recipe {
+        wants.extend(recipe.inputs.iter().map(|input| input.resource));
+    }
+    for resource in properties.wants.split(',').filter(|r| !r.trim().is_empty()) {
+        wants.push(parse_resource(resource)?);
+    }
+    anyhow::ensure!(
+        !wants.is_empty(),
+        "A business needs a recipe or something it wants"
+    );
+
+    let quotas = wants
+        .into_iter()
+        .map(|resource| Quota {
+            resource,
+            amount_per_day: properties.quota,
+        })
+        .collect();
+    let (name, recipe) = (properties.name, recipe.cloned());
+    Ok(spawn_business(world, name, position, quotas, recipe))
+}
+
+fn starting_train(
+    world: &hecs::World,
+    stations: &BTreeMap<String, Entity>,
+    properties: TrainProperties,
+) -> anyhow::Result<StartingTrain> {
+    let station = |name: &str| {
+        let station = stations.get(name).copied();
+        station.with_context(|| format!("There's no station called {name}"))
+    };
+    let start = station(&properties.station)?;
+    let segment = world.get::<&Station>(start)?.segment;
+
+    let mut orders = Vec::new();
+    for order in properties.orders.split(',').map(str::trim) {
+        let order = match order.split_once(' ') {
+            _ if order.is_empty() => continue,
+            _ if order == "unload" => Order::Unload,
+            Some(("load", resource)) => Order::LoadUntilFull(parse_resource(resource)?),
+            Some(("wait", time)) => parse_time(time)?,
+            _ => Order::GoTo(station(order)?),
+        };
+        orders.push(order);
+    }
+
+    let carries: Vec<Resource> = orders
+        .iter()
+        .filter_map(|order| match order {
+            Order::LoadUntilFull(resource) => Some(*resource),
+            _ => None,
+        })
+        .collect();
+    let wagons = vec![Cargo::new(carries, WAGON_CAPACITY); properties.wagons];
+    Ok(StartingTrain {
+        name: properties.name,
+        segment,
+        wagons,
+        orders,
+    })
+}
+
+/// Reads a time like `08:00` into an order to wait until then.
+fn parse_time(time: &str) -> anyhow::Result<Order> {
+    let time = time.trim();
+    let parsed = time.split_once(':').and_then(|(hour, minute)| {
+        let (hour, minute) = (hour.parse().ok()?, minute.parse().ok()?);
+        (hour < 24 && minute < 60).then_some(Order::WaitUntil { hour, minute })
+    });
+    parsed.with_context(|| format!("{time} isn't a time of day"))
+}
+
+/// Joins the points up into a run of track, curving smoothly through each of them. Any stations
+/// along it are added to `stations`, by name.
+fn lay_line(
+    world: &mut hecs::World,
+    mut points: Vec<TrackPoint>,
+    stations: &mut BTreeMap<String, Entity>,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(points.len() >= 2, "A line needs at least two points");
+    points.sort_by_key(|point| point.index);
+
+    let mut previous = None;
+    for (i, pair) in points.windows(2).enumerate() {
+        let (start, end) = (pair[0].position, pair[1].position);
+        // Heading from the point before to the point after gives the same heading either side.
+        let before = if i > 0 { points[i - 1].position } else { start };
+        let after = points.get(i + 2).map_or(end, |point| point.position);
+        let curve = CubicBezier::from_endpoints(start, end - before, end, after - start);
+
+        let segment = spawn_track_segment(world, curve);
+        if let Some(previous) = previous {
+            connect_segments(world, previous, segment);
+        }
+        if let Some(station) = &pair[0].station {
+            add_station(world, stations, station, segment)?;
+        }
+        previous = Some(segment);
+    }
+
+    // The last point doesn't have any track leaving it, so its station goes on the track coming in.
+    if let (Some(station), Some(segment)) = (&points[points.len() - 1].station, previous) {
+        add_station(world, stations, station, segment)?;
+    }
+    Ok(())
+}
+
+/// Trains find their stations by name, so no two can share one.
+fn add_station(
+    world: &mut hecs::World,
+    stations: &mut BTreeMap<String, Entity>,
+    name: &str,
+    segment: Entity,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !stations.contains_key(name),
+        "There's more than one station called {name}"
+    );
+    let station = spawn_station(world, name, segment, DEFAULT_CATCHMENT_RADIUS);
+    stations.insert(name.into(), station);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::{
+        production::{Deposit, Workshop},
+        town::Town,
+        track::{TrackSegment, TRACK_MODEL_LENGTH},
+    };
+
+    #[test]
+    fn loads_the_example_map() {
+        let recipes = RecipeBook::load().unwrap();
+        let mut world = hecs::World::new();
+        let (_, trains) = load_map(&mut world, &recipes, "example_map.gltf", 1000.).unwrap();
+
+        let count = |name: &str| {
+            world
+                .query::<&GLTFAsset>()
+                .iter()
+                .filter(|(_, asset)| asset.name == name)
+                .count()
+        };
+        assert_eq!(count("tree.glb"), 4);
+        assert_eq!(world.query::<&Town>().iter().count(), 1);
+        assert_eq!(world.query::<&Workshop>().iter().count(), 1);
+        let deposits = world.query::<&Deposit>().without::<&Town>().iter().count();
+        assert_eq!(deposits, 1);
+        assert_eq!(world.query::<&TrackSegment>().iter().count(), 2);
+        assert_eq!(world.query::<&Station>().iter().count(), 2);
+
+        // The points along a line are far apart, but the track between them is drawn unbroken.
+        for (_, (segment, transform)) in world.query::<(&TrackSegment, &Transform)>().iter() {
+            let chord = segment.curve.start().distance(segment.curve.end());
+            assert!(chord > 19.);
+            let drawn = transform.scale.z * TRACK_MODEL_LENGTH;
+            assert!((drawn - chord).abs() < 1e-3, "{drawn} m of {chord} m drawn");
+        }
+
+        // The shuttle starts out at Ashford Halt, and has a wagon for the passengers it picks up.
+        assert_eq!(trains.len(), 1);
+        let shuttle = &trains[0];
+        let station = |name: &str| {
+            let mut stations = world.query::<&Station>();
+            let (entity, station) = stations.iter().find(|(_, s)| s.name == name).unwrap();
+            (entity, station.segment)
+        };
+        assert_eq!(shuttle.segment, station("Ashford Halt").1);
+        assert_eq!(shuttle.wagons.len(), 1);
+        assert!(shuttle.wagons[0].accepts(Resource::Passengers));
+        assert_eq!(
+            shuttle.orders,
+            vec![
+                Order::LoadUntilFull(Resource::Passengers),
+                Order::GoTo(station("Ashford Road").0),
+                Order::Unload,
+                Order::GoTo(station("Ashford Halt").0),
+                Order::Unload,
+                Order::WaitUntil { hour: 8, minute: 0 },
+            ]
+        );
+    }
+}
@@ -0,0 +1,185 @@
+// Finding room on the map for things, so that nothing lands on top of anything else and nothing
+// lands off the edge. New things are placed by dart throwing: pick a random spot, and keep it only
+// if it's far enough from everything already there, which gives a Poisson-disk spread.
+
+use common::{
+    glam::{Vec2, Vec3},
+    rand::{rngs::StdRng, Rng},
+};
+use std::collections::HashMap;
+
+/// How many spots we'll try before giving up on fitting something in.
+const MAX_ATTEMPTS: usize = 30;
+
+/// The patch of ground something takes up. Things on the map keep theirs once they're placed, so
+/// that new track can be kept off them too.
+#[derive(Debug, Clone, Copy)]
+pub struct Footprint {
+    pub position: Vec2,
+    pub radius: f32,
+}
+
+impl Footprint {
+    /// The ground within `radius` metres of `position`, however high up that is.
+    pub fn around(position: Vec3, radius: f32) -> Self {
+        Self {
+            position: Vec2::new(position.x, position.z),
+            radius,
+        }
+    }
+
+    /// Whether something reaching `radius` metres out from `position` would be on our patch.
+    pub fn overlaps(&self, position: Vec2, radius: f32) -> bool {
+        self.position.distance(position) < self.radius + radius
+    }
+}
+
+/// Everything that's been put on the map so far, bucketed into a grid so we only have to check
+/// the neighbours of a new spot.
+pub struct Placement<F> {
+    /// Half the length of each side of the map, which is centred on the origin.
+    extent: f32,
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<Footprint>>,
+    /// The biggest footprint so far, which tells us how many cells over we need to look.
+    largest: f32,
+    /// Ground nothing's allowed on, however much room there is.
+    keep_out: F,
+}
+
+impl<F: Fn(Vec2) -> bool> Placement<F> {
+    /// An empty square map, `size` metres along each side, with nothing allowed anywhere
+    /// `keep_out` says (like the middle of a lake). Lookups are quickest when `cell_size` is about
+    /// the size of the footprints going on it.
+    pub fn new(size: f32, cell_size: f32, keep_out: F) -> Self {
+        Self {
+            extent: size / 2.,
+            cell_size,
+            cells: Default::default(),
+            largest: 0.,
+            keep_out,
+        }
+    }
+
+    /// Whether something `radius` metres from its middle to its edge could go at `position`
+    /// without sticking out over the edge of the map, overlapping anything already there, or
+    /// straying onto ground it has to keep out of.
+    pub fn is_clear(&self, position: Vec2, radius: f32) -> bool {
+        if position.abs().max_element() + radius > self.extent {
+            return false;
+        }
+        let edges = [Vec2::ZERO, Vec2::X, Vec2::NEG_X, Vec2::Y, Vec2::NEG_Y];
+        if edges
+            .iter()
+            .any(|edge| (self.keep_out)(position + *edge * radius))
+        {
+            return false;
+        }
+
+        let reach = radius + self.largest;
+        let (min_x, min_z) = self.cell(position - reach);
+        let (max_x, max_z) = self.cell(position + reach);
+        for x in min_x..=max_x {
+            for z in min_z..=max_z {
+                let Some(cell) = self.cells.get(&(x, z)) else { continue };
+                if cell
+                    .iter()
+                    .any(|footprint| footprint.overlaps(position, radius))
+                {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Claims the ground at `position`, if it's clear.
+    pub fn place(&mut self, position: Vec2, radius: f32) -> bool {
+        if !self.is_clear(position, radius) {
+            return false;
+        }
+        self.reserve(position, radius);
+        true
+    }
+
+    /// Claims the ground at `position` whether it's clear or not, for something that has to go
+    /// exactly there. Anything placed afterwards keeps out of its way.
+    pub fn reserve(&mut self, position: Vec2, radius: f32) {
+        let cell = self.cell(position);
+        self.cells
+            .entry(cell)
+            .or_default()
+            .push(Footprint { position, radius });
+        self.largest = self.largest.max(radius);
+    }
+
+    /// Finds and claims a clear spot anywhere on the map.
+    pub fn place_anywhere(&mut self, rng: &mut StdRng, radius: f32) -> Option<Vec2> {
+        let extent = self.extent - radius;
+        if extent <= 0. {
+            return None;
+        }
+        (0..MAX_ATTEMPTS).find_map(|_| {
+            let x = rng.gen_range(-extent..extent);
+            let z = rng.gen_range(-extent..extent);
+            let position = Vec2::new(x, z);
+            self.place(position, radius).then_some(position)
+        })
+    }
+
+    /// Finds and claims a clear spot between `min_distance` and `max_distance` metres from
+    /// `centre`.
+    pub fn place_near(
+        &mut self,
+        rng: &mut StdRng,
+        centre: Vec2,
+        min_distance: f32,
+        max_distance: f32,
+        radius: f32,
+    ) -> Option<Vec2> {
+        (0..MAX_ATTEMPTS).find_map(|_| {
+            let distance = rng.gen_range(min_distance..=max_distance);
+            let angle: f32 = rng.gen_range(0.0..std::f32::consts::TAU);
+            let position = centre + Vec2::from_angle(angle) * distance;
+            self.place(position, radius).then_some(position)
+        })
+    }
+
+    fn cell(&self, position: Vec2) -> (i32, i32) {
+        let cell = (position / self.cell_size).floor();
+        (cell.x as i32, cell.y as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::rand::SeedableRng;
+
+    #[test]
+    fn nothing_overlaps() {
+        let mut rng = StdRng::seed_from_u64(1234);
+        let mut placement = Placement::new(100., 5., |p: Vec2| p.x < 2.);
+        let placed: Vec<Vec2> = (0..200)
+            .filter_map(|_| placement.place_anywhere(&mut rng, 2.))
+            .collect();
+        assert!(placed.len() > 50);
+        for (i, a) in placed.iter().enumerate() {
+            assert!(a.abs().max_element() <= 48.);
+            assert!(a.x >= 0.);
+            for b in &placed[i + 1..] {
+                assert!(a.distance(*b) >= 4.);
+            }
+        }
+        assert!(!placement.place(placed[0], 1.));
+    }
+
+    #[test]
+    fn reserved_ground_is_kept_clear() {
+        let mut placement = Placement::new(100., 5., |p: Vec2| p.x < 0.);
+        placement.reserve(Vec2::ZERO, 10.);
+        assert!(!placement.is_clear(Vec2::new(12., 0.), 3.));
+        assert!(placement.is_clear(Vec2::new(13., 0.), 3.));
+        assert!(!placement.place(Vec2::new(5., 5.), 1.));
+    }
+}
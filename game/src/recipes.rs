@@ -0,0 +1,53 @@
+use asset_loader::asset_path;
+use common::anyhow::{self, Context};
+use components::{Recipe, Resource};
+use serde::Deserialize;
+
+const RECIPES_FILE: &str = "recipes.toml";
+
+/// Every recipe businesses can work to.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RecipeBook {
+    pub recipes: Vec<Recipe>,
+}
+
+impl RecipeBook {
+    /// Reads the recipe book from the assets folder.
+    pub fn load() -> anyhow::Result<Self> {
+        let path = asset_path(RECIPES_FILE);
+        let file = std::fs::read_to_string(&path).context(path.clone())?;
+        Self::parse(&file).context(path)
+    }
+
+    pub fn parse(file: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(file)?)
+    }
+
+    pub fn named(&self, name: &str) -> Option<&Recipe> {
+        self.recipes.iter().find(|recipe| recipe.name == name)
+    }
+
+    /// Recipes that need `resource` as one of their inputs.
+    pub fn using(&self, resource: Resource) -> impl Iterator<Item = &Recipe> {
+        self.recipes
+            .iter()
+            .filter(move |recipe| recipe.inputs.iter().any(|i| i.resource == resource))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_recipes() {
+        let book = RecipeBook::load().unwrap();
+        assert!(!book.recipes.is_empty());
+        assert!(book.using(Resource::Wood).any(|r| r.name == "Cobbler"));
+
+        for recipe in &book.recipes {
+            assert!(!recipe.inputs.is_empty(), "{} has no inputs", recipe.name);
+            assert!(!recipe.outputs.is_empty(), "{} has no outputs", recipe.name);
+        }
+    }
+}
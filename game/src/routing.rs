@@ -0,0 +1,203 @@
+use crate::systems::track::{exits, Direction, Junction, TrackSegment};
+use common::hecs::{self, Entity};
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
+
+/// A way of getting from one piece of track to another.
+#[derive(Debug, Clone, Default)]
+pub struct Route {
+    /// Every segment we'll travel along and which way, starting with the one we're on and ending
+    /// with the destination.
+    pub steps: Vec<(Entity, Direction)>,
+    /// The junctions we'll pass through against the points, and the branch each needs setting to.
+    pub switches: Vec<(Entity, usize)>,
+    /// Total length of track covered by `steps`.
+    pub length: f32,
+}
+
+/// Plans the shortest route from `from` to anywhere on `to` using A*, with track segments as the
+/// graph. We can only go where a train heading in the direction we're heading could actually
+/// go, so there's no doubling back at junctions.
+///
+/// Returns `None` if there's no way to get there.
+pub fn find_route(world: &hecs::World, from: (Entity, Direction), to: Entity) -> Option<Route> {
+    let goal = {
+        let destination = world.get::<&TrackSegment>(to).ok()?;
+        [destination.curve.start(), destination.curve.end()]
+    };
+
+    // Straight line distance is never longer than the track between two points, so this
+    // heuristic never overestimates.
+    let heuristic = |(segment, direction): (Entity, Direction)| {
+        let exit = world
+            .get::<&TrackSegment>(segment)
+            .unwrap()
+            .exit_point(direction);
+        goal.iter()
+            .map(|point| point.distance(exit))
+            .fold(f32::INFINITY, f32::min)
+    };
+
+    let mut open = BinaryHeap::new();
+    let mut costs = HashMap::new();
+    let mut came_from = HashMap::new();
+
+    let start_cost = world.get::<&TrackSegment>(from.0).ok()?.length();
+    costs.insert(from, start_cost);
+    open.push(Candidate {
+        estimate: start_cost + heuristic(from),
+        node: from,
+    });
+
+    while let Some(Candidate { node, .. }) = open.pop() {
+        if node.0 == to {
+            return Some(build_route(world, &came_from, node, costs[&node]));
+        }
+
+        let cost = costs[&node];
+        for next in exits(world, node.0, node.1) {
+            let next_cost = cost + world.get::<&TrackSegment>(next.0).unwrap().length();
+            if costs.get(&next).map_or(false, |c| *c <= next_cost) {
+                continue;
+            }
+
+            costs.insert(next, next_cost);
+            came_from.insert(next, node);
+            open.push(Candidate {
+                estimate: next_cost + heuristic(next),
+                node: next,
+            });
+        }
+    }
+
+    None
+}
+
+fn build_route(
+    world: &hecs::World,
+    came_from: &HashMap<(Entity, Direction), (Entity, Direction)>,
+    end: (Entity, Direction),
+    length: f32,
+) -> Route {
+    let mut steps = vec![end];
+    let mut current = end;
+    while let Some(previous) = came_from.get(&current) {
+        steps.push(*previous);
+        current = *previous;
+    }
+    steps.reverse();
+
+    let switches = steps
+        .windows(2)
+        .filter(|pair| pair[0].1 == Direction::AToB)
+        .filter_map(|pair| {
+            let junction = world.get::<&Junction>(pair[0].0).ok()?;
+            let branch = junction.branches.iter().position(|b| *b == pair[1].0)?;
+            Some((pair[0].0, branch))
+        })
+        .collect();
+
+    Route {
+        steps,
+        switches,
+        length,
+    }
+}
+
+/// An entry in the A* open set. Ordered so the `BinaryHeap` pops the lowest estimate first.
+struct Candidate {
+    estimate: f32,
+    node: (Entity, Direction),
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        spline::CubicBezier,
+        systems::track::{connect_segments, spawn_track_segment},
+    };
+    use common::glam::Vec3;
+
+    fn lay_track(
+        world: &mut hecs::World,
+        previous: Option<Entity>,
+        start: Vec3,
+        end: Vec3,
+    ) -> Entity {
+        let segment = spawn_track_segment(world, CubicBezier::straight(start, end));
+        if let Some(previous) = previous {
+            connect_segments(world, previous, segment);
+        }
+        segment
+    }
+
+    #[test]
+    fn routes_through_junctions() {
+        let mut world = hecs::World::new();
+        let start = lay_track(&mut world, None, [0., 0., 0.].into(), [2., 0., 0.].into());
+        let junction = lay_track(
+            &mut world,
+            Some(start),
+            [2., 0., 0.].into(),
+            [4., 0., 0.].into(),
+        );
+        world.insert_one(junction, Junction::default()).unwrap();
+        let straight = lay_track(
+            &mut world,
+            Some(junction),
+            [4., 0., 0.].into(),
+            [6., 0., 0.].into(),
+        );
+        let diverging = lay_track(
+            &mut world,
+            Some(junction),
+            [4., 0., 0.].into(),
+            [6., 0., 2.].into(),
+        );
+
+        // Facing the points, we need to set them for the diverging branch.
+        let route = find_route(&world, (start, Direction::AToB), diverging).unwrap();
+        assert_eq!(
+            route.steps,
+            [
+                (start, Direction::AToB),
+                (junction, Direction::AToB),
+                (diverging, Direction::AToB)
+            ]
+        );
+        assert_eq!(route.switches, [(junction, 1)]);
+        assert!((route.length - 4. - 2_f32.sqrt() * 2.).abs() < 0.01);
+
+        // Trailing through them, it doesn't matter how they're set.
+        let route = find_route(&world, (straight, Direction::BToA), start).unwrap();
+        assert_eq!(route.steps.len(), 3);
+        assert!(route.switches.is_empty());
+
+        // Trains can't double back at a junction, or go backwards.
+        assert!(find_route(&world, (straight, Direction::BToA), diverging).is_none());
+        assert!(find_route(&world, (junction, Direction::AToB), start).is_none());
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{
+    company::{Money, PLAYER_COMPANY},
+    Game,
+};
+use asset_loader::asset_path;
+use common::{
+    anyhow::{self, Context},
+    log,
+};
+use serde::Deserialize;
+
+const SCENARIO_FILE: &str = "scenario.toml";
+
+/// Something the player has to do to win.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Goal {
+    /// Have at least this much in the bank, not counting anything borrowed.
+    Cash(Money),
+    /// Meet this many daily contract quotas, all told.
+    QuotasMet(usize),
+}
+
+impl Goal {
+    pub fn is_met(&self, game: &Game) -> bool {
+        match self {
+            Goal::Cash(cash) => {
+                let balance = game.ledger.balance(PLAYER_COMPANY);
+                balance - game.finances.loan(PLAYER_COMPANY) >= *cash
+            }
+            Goal::QuotasMet(quotas) => game.contracts.quotas_met(PLAYER_COMPANY) >= *quotas,
+        }
+    }
+}
+
+impl std::fmt::Display for Goal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Goal::Cash(cash) => write!(f, "have {cash} in the bank"),
+            Goal::QuotasMet(quotas) => write!(f, "meet {quotas} contract quotas"),
+        }
+    }
+}
+
+/// What the player's trying to achieve this game, and how long they've got to do it.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    /// Every one of these has to be met to win. A scenario without any goals is won by staying in
+    /// business until the deadline, if there is one.
+    #[serde(default)]
+    pub goals: Vec<Goal>,
+    /// The last day the goals can be met on.
+    pub deadline: Option<u64>,
+    /// A hand-built map in the assets folder to play on, instead of generating one.
+    pub map: Option<String>,
+}
+
+impl Scenario {
+    /// Reads the scenario from the assets folder.
+    pub fn load() -> anyhow::Result<Self> {
+        let path = asset_path(SCENARIO_FILE);
+        let file = std::fs::read_to_string(&path).context(path.clone())?;
+        Self::parse(&file).context(path)
+    }
+
+    pub fn parse(file: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(file)?)
+    }
+}
+
+/// How the game ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Won,
+    Lost(String),
+}
+
+/// Ends the game once the player's met every goal, or once the deadline's passed.
+pub fn scenario_system(game: &mut Game) {
+    let scenario = &game.scenario;
+    if !scenario.goals.is_empty() && scenario.goals.iter().all(|goal| goal.is_met(game)) {
+        game.end(Outcome::Won);
+        return;
+    }
+
+    let Some(deadline) = scenario.deadline else { return };
+    if game.calendar.day() <= deadline {
+        return;
+    }
+    if scenario.goals.is_empty() {
+        game.end(Outcome::Won);
+    } else {
+        let missed: Vec<String> = scenario
+            .goals
+            .iter()
+            .filter(|goal| !goal.is_met(game))
+            .map(|goal| goal.to_string())
+            .collect();
+        log::info!("Day {deadline} came and went without managing to {missed:?}");
+        game.end(Outcome::Lost(format!(
+            "You didn't {} by day {deadline}.",
+            missed.join(" or ")
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_goals() {
+        let scenario = Scenario::parse(
+            r#"
+            name = "Test"
+            deadline = 10
+            goals = [{ cash = 5000 }, { quotas_met = 3 }]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(scenario.deadline, Some(10));
+        assert_eq!(scenario.goals, [Goal::Cash(5000), Goal::QuotasMet(3)]);
+        assert!(Scenario::load().is_ok());
+    }
+
+    #[test]
+    fn borrowed_money_doesnt_count_towards_cash() {
+        let mut game = Game::default();
+        let goal = Goal::Cash(1_000);
+        let day = game.calendar.day();
+        game.finances
+            .borrow(&mut game.ledger, PLAYER_COMPANY, day, 5_000)
+            .unwrap();
+        assert!(!goal.is_met(&game));
+
+        game.ledger.record(PLAYER_COMPANY, day, 1_000, "takings");
+        assert!(goal.is_met(&game));
+    }
+}
@@ -0,0 +1,180 @@
+use common::glam::{EulerRot, Quat, Vec3};
+
+/// How many chords we measure when building the arc length table. Track segments are short, so
+/// this is plenty.
+const ARC_LENGTH_SAMPLES: usize = 16;
+
+/// A cubic Bezier curve that knows how long it is, so things can travel along it at a constant
+/// speed rather than a constant `t`.
+#[derive(Debug, Clone)]
+pub struct CubicBezier {
+    pub points: [Vec3; 4],
+    /// Cumulative length of the curve at evenly spaced values of `t`.
+    arc_lengths: [f32; ARC_LENGTH_SAMPLES + 1],
+}
+
+impl CubicBezier {
+    pub fn new(points: [Vec3; 4]) -> Self {
+        let mut curve = Self {
+            points,
+            arc_lengths: [0.; ARC_LENGTH_SAMPLES + 1],
+        };
+
+        let mut previous = curve.position(0.);
+        for i in 1..=ARC_LENGTH_SAMPLES {
+            let current = curve.position(i as f32 / ARC_LENGTH_SAMPLES as f32);
+            curve.arc_lengths[i] = curve.arc_lengths[i - 1] + previous.distance(current);
+            previous = current;
+        }
+
+        curve
+    }
+
+    pub fn straight(start: Vec3, end: Vec3) -> Self {
+        let direction = end - start;
+        Self::from_endpoints(start, direction, end, direction)
+    }
+
+    /// A curve that leaves `start` heading along `start_direction` and arrives at `end` heading
+    /// along `end_direction`.
+    pub fn from_endpoints(
+        start: Vec3,
+        start_direction: Vec3,
+        end: Vec3,
+        end_direction: Vec3,
+    ) -> Self {
+        let handle_length = start.distance(end) / 3.;
+        Self::new([
+            start,
+            start + start_direction.normalize_or_zero() * handle_length,
+            end - end_direction.normalize_or_zero() * handle_length,
+            end,
+        ])
+    }
+
+    pub fn start(&self) -> Vec3 {
+        self.points[0]
+    }
+
+    pub fn end(&self) -> Vec3 {
+        self.points[3]
+    }
+
+    pub fn length(&self) -> f32 {
+        self.arc_lengths[ARC_LENGTH_SAMPLES]
+    }
+
+    pub fn position(&self, t: f32) -> Vec3 {
+        let [p0, p1, p2, p3] = self.points;
+        let u = 1. - t;
+        p0 * (u * u * u) + p1 * (3. * u * u * t) + p2 * (3. * u * t * t) + p3 * (t * t * t)
+    }
+
+    /// The (normalised) direction of travel at `t`.
+    pub fn tangent(&self, t: f32) -> Vec3 {
+        let [p0, p1, p2, p3] = self.points;
+        let u = 1. - t;
+        let derivative =
+            (p1 - p0) * (3. * u * u) + (p2 - p1) * (6. * u * t) + (p3 - p2) * (3. * t * t);
+
+        // Degenerate handles (eg. a zero length curve) have no derivative at the ends.
+        derivative
+            .try_normalize()
+            .unwrap_or_else(|| (p3 - p0).normalize_or_zero())
+    }
+
+    /// Converts a distance along the curve into a value of `t`.
+    pub fn t_at_distance(&self, distance: f32) -> f32 {
+        let length = self.length();
+        if length <= 0. {
+            return 0.;
+        }
+        let distance = distance.clamp(0., length);
+
+        // The first sample that's at least `distance` along; we interpolate back from there.
+        let index = self
+            .arc_lengths
+            .partition_point(|l| *l < distance)
+            .clamp(1, ARC_LENGTH_SAMPLES);
+        let before = self.arc_lengths[index - 1];
+        let after = self.arc_lengths[index];
+        let fraction = if after > before {
+            (distance - before) / (after - before)
+        } else {
+            0.
+        };
+
+        (index as f32 - 1. + fraction) / ARC_LENGTH_SAMPLES as f32
+    }
+
+    pub fn position_at_distance(&self, distance: f32) -> Vec3 {
+        self.position(self.t_at_distance(distance))
+    }
+
+    pub fn tangent_at_distance(&self, distance: f32) -> Vec3 {
+        self.tangent(self.t_at_distance(distance))
+    }
+}
+
+/// Rotation that points a model's +Z axis along `tangent`, keeping it upright.
+pub fn rotation_from_tangent(tangent: Vec3) -> Quat {
+    let yaw = tangent.x.atan2(tangent.z);
+    let pitch = -tangent.y.clamp(-1., 1.).asin();
+    Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_straight_curve_is_as_long_as_its_chord() {
+        let curve = CubicBezier::straight(Vec3::ZERO, Vec3::new(30., 0., 40.));
+        assert!((curve.length() - 50.).abs() < 1e-3);
+
+        for distance in [0., 10., 25., 42.5, 50.] {
+            let expected = Vec3::new(0.6, 0., 0.8) * distance;
+            assert!(curve.position_at_distance(distance).distance(expected) < 1e-3);
+        }
+    }
+
+    #[test]
+    fn distances_off_the_ends_clamp_to_the_ends() {
+        let curve = CubicBezier::straight(Vec3::ZERO, Vec3::X * 10.);
+        assert_eq!(curve.t_at_distance(-5.), 0.);
+        assert_eq!(curve.t_at_distance(15.), 1.);
+        assert_eq!(curve.position_at_distance(15.), curve.end());
+
+        let point = CubicBezier::straight(Vec3::ONE, Vec3::ONE);
+        assert_eq!(point.length(), 0.);
+        assert_eq!(point.t_at_distance(1.), 0.);
+    }
+
+    #[test]
+    fn a_quarter_circle_is_measured_along_the_arc() {
+        // The usual Bezier approximation of a quarter circle, which is within a fraction of a
+        // percent of the real thing.
+        let radius = 20.;
+        let handle = radius * 0.552_284_8;
+        let curve = CubicBezier::new([
+            Vec3::new(radius, 0., 0.),
+            Vec3::new(radius, 0., handle),
+            Vec3::new(handle, 0., radius),
+            Vec3::new(0., 0., radius),
+        ]);
+
+        let arc = std::f32::consts::FRAC_PI_2 * radius;
+        assert!((curve.length() - arc).abs() < arc * 0.001);
+
+        // Equal distances along the curve should sweep out equal angles.
+        for step in 0..=6 {
+            let angle = std::f32::consts::FRAC_PI_2 * step as f32 / 6.;
+            let expected = Vec3::new(angle.cos(), 0., angle.sin()) * radius;
+            let position = curve.position_at_distance(curve.length() * step as f32 / 6.);
+            assert!(
+                position.distance(expected) < 0.05,
+                "{position} should be near {expected}"
+            );
+        }
+    }
+}
@@ -0,0 +1,159 @@
+// The little network a game starts out with, so there's something running from the first minute:
+// a main line heading east from the middle of the map, which splits at a junction into a straight
+// and a branch that climbs off over the hills to a wood deposit, with a station at either end of
+// each. It's laid exactly where it's meant to go, whatever's there, so it has to go down before
+// anything else does. Two trains come with it, to show what can be done with it.
+
+use crate::{
+    map_generation::spawn_deposit,
+    spline::CubicBezier,
+    systems::{
+        orders::Order,
+        signalling::spawn_signal,
+        station::{spawn_station, DEFAULT_CATCHMENT_RADIUS},
+        track::{
+            connect_segments, spawn_track_segment, Direction, Junction, TrackSegment,
+            TRACK_CLEARANCE,
+        },
+        train::{Cargo, StartingTrain, WAGON_CAPACITY},
+    },
+    terrain::Terrain,
+};
+use common::{
+    glam::Vec3,
+    hecs::{self, Entity},
+};
+use components::{Collider, Resource};
+
+/// The parts of the starter network that anything else needs to know about.
+#[derive(Debug, Clone, Copy)]
+pub struct StarterNetwork {
+    /// The first segment of the main line, where Riverside is.
+    pub main_line: Entity,
+    /// The segment just past the junction on the straight, where Junction Road is.
+    pub straight: Entity,
+    /// The wood up by Hilltop.
+    pub deposit: Entity,
+    pub riverside: Entity,
+    pub hilltop: Entity,
+    pub junction_road: Entity,
+    pub long_acre: Entity,
+}
+
+impl StarterNetwork {
+    /// The Express, taking wood from Hilltop down to Riverside, and The Local, running bread up
+    /// and down the straight.
+    pub fn trains(&self) -> Vec<StartingTrain> {
+        let express = StartingTrain {
+            name: "The Express".into(),
+            segment: self.main_line,
+            wagons: vec![
+                Cargo::new(vec![Resource::Wood, Resource::Coal], WAGON_CAPACITY),
+                Cargo::new(vec![Resource::Wood, Resource::Coal], WAGON_CAPACITY),
+                Cargo::new(vec![Resource::Fish, Resource::Crabs], WAGON_CAPACITY),
+            ],
+            orders: vec![
+                Order::GoTo(self.hilltop),
+                Order::LoadUntilFull(Resource::Wood),
+                Order::GoTo(self.riverside),
+                Order::Unload,
+                Order::WaitUntil { hour: 8, minute: 0 },
+            ],
+        };
+        let local = StartingTrain {
+            name: "The Local".into(),
+            segment: self.straight,
+            wagons: vec![Cargo::new(vec![Resource::Bread], WAGON_CAPACITY)],
+            orders: vec![
+                Order::GoTo(self.long_acre),
+                Order::Unload,
+                Order::GoTo(self.junction_road),
+                Order::Unload,
+            ],
+        };
+        vec![express, local]
+    }
+}
+
+pub fn lay_starter_network(world: &mut hecs::World, terrain: &Terrain) -> StarterNetwork {
+    let origin = terrain.surface(Vec3::ZERO);
+    let main_line = spawn_track_segment(
+        world,
+        CubicBezier::straight(
+            origin + Vec3::Y * TRACK_CLEARANCE,
+            terrain.surface(Vec3::X * 2.) + Vec3::Y * TRACK_CLEARANCE,
+        ),
+    );
+    let main_line_end = create_track_segments(world, terrain, main_line, 5, Vec3::X * 2.);
+    spawn_signal(world, main_line_end, Direction::AToB);
+
+    // Fan the main line out into a straight and a diverging branch.
+    let junction = extend_track(world, terrain, main_line_end, Vec3::X * 2.);
+    world
+        .insert(junction, (Junction::default(), Collider::default()))
+        .unwrap();
+    let straight = extend_track(world, terrain, junction, Vec3::X * 2.);
+    let straight_end = create_track_segments(world, terrain, straight, 4, Vec3::X * 2.);
+    // The diverging branch heads off over the hills, wherever they happen to be.
+    let branch_end = create_track_segments(world, terrain, junction, 5, [2., 0., 2.].into());
+
+    // Give the hilltop something worth going up there for.
+    let hilltop_position = world.get::<&TrackSegment>(branch_end).unwrap().curve.end();
+    let deposit = spawn_deposit(
+        world,
+        Resource::Wood,
+        terrain.surface(hilltop_position + Vec3::new(5., 0., 5.)),
+        40,
+    );
+    let riverside = spawn_station(world, "Riverside", main_line, DEFAULT_CATCHMENT_RADIUS);
+    let hilltop = spawn_station(world, "Hilltop", branch_end, DEFAULT_CATCHMENT_RADIUS);
+    // The straight gets a shuttle of its own, which never needs to cross the points.
+    let junction_road = spawn_station(world, "Junction Road", straight, DEFAULT_CATCHMENT_RADIUS);
+    let long_acre = spawn_station(world, "Long Acre", straight_end, DEFAULT_CATCHMENT_RADIUS);
+
+    StarterNetwork {
+        main_line,
+        straight,
+        deposit,
+        riverside,
+        hilltop,
+        junction_road,
+        long_acre,
+    }
+}
+
+/// Lays a run of segments off the end of `start`, each `step` further along (give or take the
+/// lie of the land), returning the last one.
+fn create_track_segments(
+    world: &mut hecs::World,
+    terrain: &Terrain,
+    start: Entity,
+    segments_remaining: usize,
+    step: Vec3,
+) -> Entity {
+    if segments_remaining == 0 {
+        return start;
+    }
+
+    let a = extend_track(world, terrain, start, step);
+    create_track_segments(world, terrain, a, segments_remaining - 1, step)
+}
+
+/// Adds a segment to the `b` end of `previous` that ends `step` away on the ground, curving
+/// smoothly from the direction `previous` was heading in.
+fn extend_track(
+    world: &mut hecs::World,
+    terrain: &Terrain,
+    previous: Entity,
+    step: Vec3,
+) -> Entity {
+    let curve = {
+        let previous = world.get::<&TrackSegment>(previous).unwrap();
+        let start = previous.curve.end();
+        let end = terrain.surface(start + step) + Vec3::Y * TRACK_CLEARANCE;
+        CubicBezier::from_endpoints(start, previous.curve.tangent(1.), end, end - start)
+    };
+    let segment = spawn_track_segment(world, curve);
+    connect_segments(world, previous, segment);
+    segment
+}
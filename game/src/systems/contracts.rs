@@ -0,0 +1,294 @@
+use super::{
+    production::Workshop,
+    station::{Consignment, Station},
+    town::Town,
+    train::Train,
+};
+use crate::{
+    company::{CompanyId, Money},
+    Game, GameEvent,
+};
+use common::{hecs::Entity, log};
+use components::{Business, Resource, Transform};
+use std::collections::BTreeMap;
+
+/// What a business charges for each unit a quota falls short by, as a share of the market price.
+pub const QUOTA_PENALTY_RATE: f32 = 0.5;
+
+/// Keeps track of who's supplying which business, and how they're getting on today.
+#[derive(Debug, Clone, Default)]
+pub struct Contracts {
+    /// A business signs up with the first company to make a delivery to it, and holds them to
+    /// its contract from then on.
+    holders: BTreeMap<Entity, CompanyId>,
+    /// How much of each resource each business has had so far today.
+    delivered: BTreeMap<(Entity, Resource), usize>,
+    /// What today's deliveries are worth, to be paid out when the day's over.
+    earned: BTreeMap<(Entity, Resource), Money>,
+    /// How many daily quotas each company has met, over all time.
+    quotas_met: BTreeMap<CompanyId, usize>,
+    /// The day `delivered` is counting towards.
+    day: u64,
+}
+
+impl Contracts {
+    pub fn holder(&self, business: Entity) -> Option<CompanyId> {
+        self.holders.get(&business).copied()
+    }
+
+    pub fn quotas_met(&self, company: CompanyId) -> usize {
+        self.quotas_met.get(&company).copied().unwrap_or(0)
+    }
+
+    /// How much of `resource` the business has had today.
+    pub fn delivered_today(&self, business: Entity, resource: Resource) -> usize {
+        self.delivered
+            .get(&(business, resource))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+/// Hands each delivery over to a business that wants it, and settles up every contract at the
+/// end of each day. Businesses with a workshop put what they're given to work. Anything nobody
+/// wants gets sold on the spot, at market price.
+///
+/// Deliveries arrive as events, which only last a frame, so this runs once a frame rather than
+/// once a tick.
+pub fn contracts_system(game: &mut Game) {
+    for event in &game.events {
+        let GameEvent::Delivery {
+            train,
+            station,
+            resource,
+            amount,
+            consignment,
+        } = event
+        else {
+            continue;
+        };
+        let Ok(owner) = game.world.get::<&Train>(*train).map(|t| t.owner) else { continue };
+        let boarded_here = consignment.map(|c| c.station) == Some(*station);
+        if *resource == Resource::Passengers && boarded_here {
+            // They've just gone round in a circle, and aren't paying for the privilege.
+            continue;
+        }
+        let value = delivery_value(game, *station, *resource, *amount, *consignment);
+
+        let Some(business) = customer(game, *station, *resource) else {
+            let station_name = game
+                .world
+                .get::<&Station>(*station)
+                .map_or_else(|_| "nowhere".into(), |s| s.name.clone());
+            let description = format!("sold {amount} {resource:?} at {station_name}");
+            game.ledger
+                .record(owner, game.calendar.day(), value, description);
+            game.market.record_sale(*resource, *amount);
+            continue;
+        };
+
+        if let Ok(mut workshop) = game.world.get::<&mut Workshop>(business) {
+            workshop.inputs.add(*resource, *amount);
+        }
+        if let Ok(mut town) = game.world.get::<&mut Town>(business) {
+            town.receive(*resource, *amount);
+        }
+
+        let contracts = &mut game.contracts;
+        contracts.holders.entry(business).or_insert(owner);
+        let delivered = contracts
+            .delivered
+            .entry((business, *resource))
+            .or_default();
+        *delivered += amount;
+        *contracts.earned.entry((business, *resource)).or_default() += value;
+    }
+
+    let today = game.calendar.day();
+    if today != game.contracts.day {
+        settle_up(game);
+        game.contracts.delivered.clear();
+        game.contracts.earned.clear();
+        game.contracts.day = today;
+    }
+}
+
+/// What goods dropped off at `station` are worth, given how far they've come and how long it
+/// took them.
+fn delivery_value(
+    game: &Game,
+    station: Entity,
+    resource: Resource,
+    amount: usize,
+    consignment: Option<Consignment>,
+) -> Money {
+    let position = |entity| game.world.get::<&Transform>(entity).map(|t| t.position);
+    let (distance, transit) = match consignment {
+        Some(consignment) => {
+            let distance = match (position(consignment.station), position(station)) {
+                (Ok(from), Ok(to)) => from.distance(to),
+                _ => 0.,
+            };
+            let transit = game.calendar.ticks().saturating_sub(consignment.loaded_at);
+            (distance, game.calendar.days(transit))
+        }
+        None => (0., 0.),
+    };
+    game.market.value(resource, amount, distance, transit)
+}
+
+/// The business around `station` that `resource` should go to: whoever is furthest from meeting
+/// today's quota for it.
+fn customer(game: &Game, station: Entity, resource: Resource) -> Option<Entity> {
+    let station = game.world.get::<&Station>(station).ok()?;
+    station
+        .catchment
+        .iter()
+        .filter_map(|entity| {
+            let business = game.world.get::<&Business>(*entity).ok()?;
+            let quota = business
+                .contract
+                .quotas
+                .iter()
+                .find(|quota| quota.resource == resource)?;
+            let delivered = game.contracts.delivered_today(*entity, resource);
+            Some((quota.amount_per_day.saturating_sub(delivered), *entity))
+        })
+        .max_by_key(|(shortfall, entity)| (*shortfall, std::cmp::Reverse(*entity)))
+        .map(|(_, entity)| entity)
+}
+
+/// Pays out for everything delivered yesterday, and charges for every quota that fell short.
+/// Towns don't charge anything; they'll just shrink.
+fn settle_up(game: &mut Game) {
+    let day = game.contracts.day;
+    for (business_entity, company) in &game.contracts.holders {
+        let Ok(business) = game.world.get::<&Business>(*business_entity) else { continue };
+        let is_town = game.world.get::<&Town>(*business_entity).is_ok();
+        for quota in &business.contract.quotas {
+            let delivered = game
+                .contracts
+                .delivered_today(*business_entity, quota.resource);
+            let required = quota.amount_per_day;
+
+            let earned = game
+                .contracts
+                .earned
+                .get(&(*business_entity, quota.resource))
+                .copied()
+                .unwrap_or(0);
+            if earned > 0 {
+                let description = format!("{delivered} {:?} for {}", quota.resource, business.name);
+                game.ledger.record(*company, day, earned, description);
+            }
+
+            if delivered >= required {
+                *game.contracts.quotas_met.entry(*company).or_default() += 1;
+            } else if !is_town {
+                let shortfall = required - delivered;
+                log::info!(
+                    "{} is {shortfall} {:?} short of its quota",
+                    business.name,
+                    quota.resource
+                );
+                let description = format!(
+                    "missing {shortfall} {:?} for {}",
+                    quota.resource, business.name
+                );
+                let price = game.market.price(quota.resource) as f32;
+                let penalty = (shortfall as f32 * price * QUOTA_PENALTY_RATE).round() as Money;
+                game.ledger.record(*company, day, -penalty, description);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        company::PLAYER_COMPANY,
+        test_helpers::{next_day, station_with_train},
+    };
+    use components::{Contract, Quota};
+
+    /// A game with a train, and a station serving a business for each of `quotas` (each wanting
+    /// that much coal a day), with the contracts caught up to the calendar.
+    fn game_with(quotas: &[usize]) -> (Game, Entity, Entity, Vec<Entity>) {
+        let mut game = Game::default();
+        let businesses: Vec<Entity> = quotas
+            .iter()
+            .map(|amount_per_day| {
+                let quota = Quota {
+                    resource: Resource::Coal,
+                    amount_per_day: *amount_per_day,
+                };
+                let contract = Contract {
+                    quotas: vec![quota],
+                };
+                let name = "Business".into();
+                let business = Business { name, contract };
+                game.world.spawn((business, Transform::default()))
+            })
+            .collect();
+
+        let (train, station) = station_with_train(&mut game, businesses.clone(), Vec::new());
+        contracts_system(&mut game);
+        (game, train, station, businesses)
+    }
+
+    fn deliver(game: &mut Game, train: Entity, station: Entity, amount: usize) {
+        game.events.push_back(GameEvent::Delivery {
+            train,
+            station,
+            resource: Resource::Coal,
+            amount,
+            consignment: None,
+        });
+        contracts_system(game);
+        game.events.clear();
+    }
+
+    fn coal_delivered(game: &Game, business: Entity) -> usize {
+        game.contracts.delivered_today(business, Resource::Coal)
+    }
+
+    #[test]
+    fn deliveries_go_to_whoever_is_furthest_from_their_quota() {
+        let (mut game, train, station, businesses) = game_with(&[10, 30, 30]);
+
+        // The last two are just as far off, so it goes to the one that was there first.
+        deliver(&mut game, train, station, 5);
+        assert_eq!(coal_delivered(&game, businesses[1]), 5);
+        deliver(&mut game, train, station, 5);
+        assert_eq!(coal_delivered(&game, businesses[2]), 5);
+
+        assert_eq!(game.contracts.holder(businesses[1]), Some(PLAYER_COMPANY));
+        assert_eq!(game.contracts.holder(businesses[0]), None);
+    }
+
+    #[test]
+    fn settles_up_at_the_end_of_each_day() {
+        let (mut game, train, station, businesses) = game_with(&[10, 10]);
+        let price = game.market.price(Resource::Coal);
+        let penalty = |shortfall: usize| {
+            (shortfall as f32 * price as f32 * QUOTA_PENALTY_RATE).round() as Money
+        };
+
+        // One quota met, and the other four short.
+        deliver(&mut game, train, station, 10);
+        deliver(&mut game, train, station, 6);
+        assert_eq!(game.ledger.balance(PLAYER_COMPANY), 0);
+        next_day(&mut game, contracts_system);
+        let balance = game.ledger.balance(PLAYER_COMPANY);
+        assert_eq!(balance, 16 * price - penalty(4));
+        assert_eq!(game.contracts.quotas_met(PLAYER_COMPANY), 1);
+
+        // Yesterday's deliveries don't count towards today, but the contracts still stand.
+        assert_eq!(coal_delivered(&game, businesses[0]), 0);
+        next_day(&mut game, contracts_system);
+        let missed = game.ledger.balance(PLAYER_COMPANY) - balance;
+        assert_eq!(missed, -2 * penalty(10));
+        assert_eq!(game.contracts.quotas_met(PLAYER_COMPANY), 1);
+    }
+}
@@ -0,0 +1,312 @@
+use super::{station::Station, track::TrackSegment, train::Train};
+use crate::{
+    company::{CompanyId, Ledger, Money, PLAYER_COMPANY},
+    scenario::Outcome,
+    Game,
+};
+use common::log;
+use std::collections::BTreeMap;
+
+/// What the player's company has in the bank on the first day.
+pub const STARTING_CASH: Money = 100_000;
+
+pub const TRACK_COST_PER_METRE: Money = 1_000;
+pub const BRIDGE_COST_PER_METRE: Money = 5_000;
+pub const STATION_COST: Money = 15_000;
+pub const LOCOMOTIVE_COST: Money = 30_000;
+pub const WAGON_COST: Money = 5_000;
+
+/// Daily upkeep, charged at the end of every day.
+const TRACK_MAINTENANCE_PER_METRE: Money = 10;
+const STATION_MAINTENANCE: Money = 200;
+const LOCOMOTIVE_RUNNING_COST: Money = 500;
+const WAGON_RUNNING_COST: Money = 50;
+
+/// The most the bank will lend any one company.
+pub const MAX_LOAN: Money = 200_000;
+/// Interest charged on what's owed, every day.
+const DAILY_INTEREST_RATE: f64 = 0.002;
+
+/// A company that ends this many days in a row with a negative balance goes bankrupt.
+const DAYS_IN_THE_RED_BEFORE_BANKRUPTCY: u32 = 3;
+
+/// Why some money couldn't be spent or borrowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FinanceError {
+    CannotAfford { cost: Money, cash: Money },
+    LoanLimit { owed: Money, limit: Money },
+}
+
+impl std::fmt::Display for FinanceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FinanceError::CannotAfford { cost, cash } => {
+                write!(f, "that costs {cost}, but there's only {cash} in the bank")
+            }
+            FinanceError::LoanLimit { owed, limit } => {
+                write!(f, "the bank won't lend more than {limit}, with {owed} owed")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FinanceError {}
+
+/// Pays for something out of the company's cash, as long as there's enough of it.
+pub fn charge<S: Into<String>>(
+    ledger: &mut Ledger,
+    company: CompanyId,
+    day: u64,
+    cost: Money,
+    description: S,
+) -> Result<(), FinanceError> {
+    let cash = ledger.balance(company);
+    if cash < cost {
+        return Err(FinanceError::CannotAfford { cost, cash });
+    }
+    ledger.record(company, day, -cost, description);
+    Ok(())
+}
+
+/// Who owes the bank what, and who's been struggling to pay their way.
+#[derive(Debug, Clone, Default)]
+pub struct Finances {
+    loans: BTreeMap<CompanyId, Money>,
+    days_in_the_red: BTreeMap<CompanyId, u32>,
+    /// The day running costs were last charged for.
+    day: u64,
+}
+
+impl Finances {
+    /// What the company owes the bank.
+    pub fn loan(&self, company: CompanyId) -> Money {
+        self.loans.get(&company).copied().unwrap_or(0)
+    }
+
+    /// Borrows `amount` from the bank, up to [`MAX_LOAN`] in total.
+    pub fn borrow(
+        &mut self,
+        ledger: &mut Ledger,
+        company: CompanyId,
+        day: u64,
+        amount: Money,
+    ) -> Result<(), FinanceError> {
+        let owed = self.loan(company);
+        if owed + amount > MAX_LOAN {
+            return Err(FinanceError::LoanLimit {
+                owed,
+                limit: MAX_LOAN,
+            });
+        }
+        self.loans.insert(company, owed + amount);
+        ledger.record(company, day, amount, "loan");
+        Ok(())
+    }
+
+    /// Pays back up to `amount` of the company's loan.
+    pub fn repay(
+        &mut self,
+        ledger: &mut Ledger,
+        company: CompanyId,
+        day: u64,
+        amount: Money,
+    ) -> Result<(), FinanceError> {
+        let owed = self.loan(company);
+        let amount = amount.min(owed);
+        if amount <= 0 {
+            return Ok(());
+        }
+        charge(ledger, company, day, amount, "loan repayment")?;
+        self.loans.insert(company, owed - amount);
+        Ok(())
+    }
+}
+
+/// Charges everyone their running costs and interest at the end of each day, and puts any company
+/// that's been in the red too long out of business. If that's the player, the game's over.
+pub fn finances_system(game: &mut Game) {
+    let today = game.calendar.day();
+    if today == game.finances.day {
+        return;
+    }
+    let day = std::mem::replace(&mut game.finances.day, today);
+    if day == 0 {
+        // Nothing's been running yet.
+        return;
+    }
+
+    let mut costs: BTreeMap<CompanyId, Vec<(Money, String)>> = BTreeMap::new();
+
+    // There's only the player laying track and building stations for now.
+    let track_length: f32 = game
+        .world
+        .query::<&TrackSegment>()
+        .iter()
+        .map(|(_, segment)| segment.length())
+        .sum();
+    let track_cost = (track_length * TRACK_MAINTENANCE_PER_METRE as f32).round() as Money;
+    let stations = game.world.query::<&Station>().iter().count() as Money;
+    let infrastructure = costs.entry(PLAYER_COMPANY).or_default();
+    infrastructure.push((track_cost, "track maintenance".into()));
+    infrastructure.push((stations * STATION_MAINTENANCE, "station maintenance".into()));
+
+    for (_, train) in game.world.query::<&Train>().iter() {
+        let wagons = train.cars.len().saturating_sub(1) as Money;
+        let cost = LOCOMOTIVE_RUNNING_COST + wagons * WAGON_RUNNING_COST;
+        let description = format!("running {}", train.name);
+        costs
+            .entry(train.owner)
+            .or_default()
+            .push((cost, description));
+    }
+
+    for (company, owed) in &game.finances.loans {
+        let interest = (*owed as f64 * DAILY_INTEREST_RATE).round() as Money;
+        let description = format!("interest on a loan of {owed}");
+        costs
+            .entry(*company)
+            .or_default()
+            .push((interest, description));
+    }
+
+    for (company, costs) in costs {
+        for (cost, description) in costs {
+            if cost > 0 {
+                game.ledger.record(company, day, -cost, description);
+            }
+        }
+
+        let days_in_the_red = game.finances.days_in_the_red.entry(company).or_default();
+        if game.ledger.balance(company) >= 0 {
+            *days_in_the_red = 0;
+            continue;
+        }
+        *days_in_the_red += 1;
+        if *days_in_the_red >= DAYS_IN_THE_RED_BEFORE_BANKRUPTCY {
+            log::warn!("{company:?} has gone bankrupt");
+            if company == PLAYER_COMPANY {
+                game.end(Outcome::Lost("You went bankrupt.".into()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::next_day;
+
+    /// A game with nothing to pay for, and the finances caught up to the calendar.
+    fn game_with_cash(cash: Money) -> Game {
+        let mut game = Game::default();
+        let day = game.calendar.day();
+        game.ledger
+            .record(PLAYER_COMPANY, day, cash, "starting capital");
+        finances_system(&mut game);
+        game
+    }
+
+    #[test]
+    fn cant_spend_more_than_is_in_the_bank() {
+        let mut ledger = Ledger::default();
+        ledger.record(PLAYER_COMPANY, 1, 100, "starting capital");
+
+        let result = charge(&mut ledger, PLAYER_COMPANY, 1, 150, "something dear");
+        let expected = FinanceError::CannotAfford {
+            cost: 150,
+            cash: 100,
+        };
+        assert_eq!(result, Err(expected));
+        assert_eq!(ledger.balance(PLAYER_COMPANY), 100);
+
+        assert_eq!(
+            charge(&mut ledger, PLAYER_COMPANY, 1, 100, "all of it"),
+            Ok(())
+        );
+        assert_eq!(ledger.balance(PLAYER_COMPANY), 0);
+    }
+
+    #[test]
+    fn borrowing_and_repaying_stay_within_limits() {
+        let mut ledger = Ledger::default();
+        let mut finances = Finances::default();
+
+        assert_eq!(
+            finances.borrow(&mut ledger, PLAYER_COMPANY, 1, MAX_LOAN),
+            Ok(())
+        );
+        let result = finances.borrow(&mut ledger, PLAYER_COMPANY, 1, 1);
+        let expected = FinanceError::LoanLimit {
+            owed: MAX_LOAN,
+            limit: MAX_LOAN,
+        };
+        assert_eq!(result, Err(expected));
+        assert_eq!(finances.loan(PLAYER_COMPANY), MAX_LOAN);
+        assert_eq!(ledger.balance(PLAYER_COMPANY), MAX_LOAN);
+
+        // Paying back more than is owed only pays back what's owed.
+        let spare = 1_000;
+        ledger.record(PLAYER_COMPANY, 1, spare, "takings");
+        let result = finances.repay(&mut ledger, PLAYER_COMPANY, 1, MAX_LOAN * 2);
+        assert_eq!(result, Ok(()));
+        assert_eq!(finances.loan(PLAYER_COMPANY), 0);
+        assert_eq!(ledger.balance(PLAYER_COMPANY), spare);
+
+        // It has to come out of the bank, though.
+        finances
+            .borrow(&mut ledger, PLAYER_COMPANY, 1, 500)
+            .unwrap();
+        charge(&mut ledger, PLAYER_COMPANY, 1, spare + 500, "spending it").unwrap();
+        let result = finances.repay(&mut ledger, PLAYER_COMPANY, 1, 500);
+        let expected = FinanceError::CannotAfford { cost: 500, cash: 0 };
+        assert_eq!(result, Err(expected));
+        assert_eq!(finances.loan(PLAYER_COMPANY), 500);
+    }
+
+    #[test]
+    fn interest_is_charged_every_day() {
+        let mut game = game_with_cash(0);
+        let day = game.calendar.day();
+        let loan = 100_000;
+        game.finances
+            .borrow(&mut game.ledger, PLAYER_COMPANY, day, loan)
+            .unwrap();
+
+        let interest = (loan as f64 * DAILY_INTEREST_RATE).round() as Money;
+        next_day(&mut game, finances_system);
+        assert_eq!(game.ledger.balance(PLAYER_COMPANY), loan - interest);
+        next_day(&mut game, finances_system);
+        assert_eq!(game.ledger.balance(PLAYER_COMPANY), loan - 2 * interest);
+    }
+
+    #[test]
+    fn going_bankrupt_ends_the_game() {
+        let mut game = game_with_cash(-1);
+
+        for _ in 1..DAYS_IN_THE_RED_BEFORE_BANKRUPTCY {
+            next_day(&mut game, finances_system);
+            assert!(!game.game_over);
+        }
+        next_day(&mut game, finances_system);
+        assert!(game.game_over);
+        assert!(matches!(game.outcome, Some(Outcome::Lost(_))));
+    }
+
+    #[test]
+    fn getting_back_in_the_black_starts_the_count_again() {
+        let mut game = game_with_cash(-1);
+
+        for _ in 1..DAYS_IN_THE_RED_BEFORE_BANKRUPTCY {
+            next_day(&mut game, finances_system);
+        }
+        let day = game.calendar.day();
+        game.ledger.record(PLAYER_COMPANY, day, 1, "a lucky find");
+        next_day(&mut game, finances_system);
+        game.ledger
+            .record(PLAYER_COMPANY, day, -1, "an unlucky loss");
+        for _ in 1..DAYS_IN_THE_RED_BEFORE_BANKRUPTCY {
+            next_day(&mut game, finances_system);
+        }
+        assert!(!game.game_over);
+    }
+}
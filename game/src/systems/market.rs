@@ -0,0 +1,199 @@
+use super::production::{Deposit, Workshop};
+use crate::{company::Money, Game};
+use common::{enum_iterator, log};
+use components::{Business, Resource};
+use std::collections::BTreeMap;
+
+/// Prices never fall below this fraction of a resource's base price, or climb above its inverse.
+const MIN_PRICE_FACTOR: f32 = 0.25;
+/// How far prices move towards where supply and demand say they should be, each day.
+const PRICE_ADJUSTMENT: f32 = 0.25;
+/// Goods are worth twice as much once they've been carried this far, in metres.
+const DISTANCE_FOR_DOUBLE_VALUE: f32 = 500.;
+/// Goods keep their value for this many days on the move.
+const DAYS_IN_TRANSIT_BEFORE_DECAY: f32 = 1.;
+/// After that, they lose this much of it every day, down to `MIN_FRESHNESS`.
+const DECAY_PER_DAY: f32 = 0.2;
+const MIN_FRESHNESS: f32 = 0.25;
+
+/// What a unit of each resource is worth when there's just as much of it going as is wanted.
+pub fn base_price(resource: Resource) -> Money {
+    match resource {
+        Resource::Wood => 8,
+        Resource::Coal => 10,
+        Resource::Uranium => 40,
+        Resource::Boots => 30,
+        Resource::Fish => 12,
+        Resource::Bread => 15,
+        Resource::HorseMeat => 14,
+        Resource::Crabs => 16,
+        Resource::Amethyst => 35,
+        Resource::GolfBalls => 25,
+        Resource::Passengers => 20,
+    }
+}
+
+/// The going rate for every resource, worked out each day from how much of it the world makes
+/// and how much of it the world wants.
+#[derive(Debug, Clone)]
+pub struct Market {
+    /// Price per unit. Kept as a float so small daily movements add up.
+    prices: BTreeMap<Resource, f32>,
+    /// Units sold on the spot market today, which count towards tomorrow's supply.
+    sold: BTreeMap<Resource, usize>,
+    /// The day prices were last set for.
+    day: u64,
+}
+
+impl Default for Market {
+    fn default() -> Self {
+        Self {
+            prices: enum_iterator::all::<Resource>()
+                .map(|resource| (resource, base_price(resource) as f32))
+                .collect(),
+            sold: Default::default(),
+            day: 0,
+        }
+    }
+}
+
+impl Market {
+    /// What a unit of `resource` is going for today.
+    pub fn price(&self, resource: Resource) -> Money {
+        self.prices[&resource].round() as Money
+    }
+
+    /// What `amount` of `resource` is worth once it's been carried `distance` metres, taking
+    /// `days_in_transit` days. The further it's come the more it's worth, but the longer it's
+    /// taken the less it's worth.
+    pub fn value(
+        &self,
+        resource: Resource,
+        amount: usize,
+        distance: f32,
+        days_in_transit: f32,
+    ) -> Money {
+        let late_by = (days_in_transit - DAYS_IN_TRANSIT_BEFORE_DECAY).max(0.);
+        let freshness = (1. - late_by * DECAY_PER_DAY).max(MIN_FRESHNESS);
+        let distance_bonus = 1. + distance / DISTANCE_FOR_DOUBLE_VALUE;
+
+        let value = self.prices[&resource] * amount as f32 * distance_bonus * freshness;
+        value.round() as Money
+    }
+
+    /// Notes down a spot sale, so the extra supply shows up in tomorrow's prices.
+    pub fn record_sale(&mut self, resource: Resource, amount: usize) {
+        *self.sold.entry(resource).or_default() += amount;
+    }
+}
+
+/// Resets prices at the start of every day.
+pub fn market_system(game: &mut Game) {
+    let today = game.calendar.day();
+    if today == game.market.day {
+        return;
+    }
+
+    let (supply, demand) = supply_and_demand(game);
+    let market = &mut game.market;
+    for (resource, price) in market.prices.iter_mut() {
+        let sold = market.sold.get(resource).copied().unwrap_or(0);
+        let supply = supply.get(resource).copied().unwrap_or(0) + sold;
+        let demand = demand.get(resource).copied().unwrap_or(0);
+
+        let base = base_price(*resource) as f32;
+        let target = base * (demand + 1) as f32 / (supply + 1) as f32;
+        let target = target.clamp(base * MIN_PRICE_FACTOR, base / MIN_PRICE_FACTOR);
+        *price += (target - *price) * PRICE_ADJUSTMENT;
+        log::debug!("{resource:?} is going for {price:.1} on day {today}");
+    }
+    market.sold.clear();
+    market.day = today;
+}
+
+/// Units of each resource the world makes in a day, and units it wants.
+fn supply_and_demand(game: &Game) -> (BTreeMap<Resource, usize>, BTreeMap<Resource, usize>) {
+    let mut supply: BTreeMap<Resource, usize> = BTreeMap::new();
+    let mut demand: BTreeMap<Resource, usize> = BTreeMap::new();
+
+    for (_, (deposit, resource)) in game.world.query::<(&Deposit, &Resource)>().iter() {
+        *supply.entry(*resource).or_default() += deposit.rate as usize;
+    }
+    for (_, workshop) in game.world.query::<&Workshop>().iter() {
+        let batches = workshop.recipe.batches_per_day as usize;
+        for output in &workshop.recipe.outputs {
+            *supply.entry(output.resource).or_default() += output.amount * batches;
+        }
+    }
+    for (_, business) in game.world.query::<&Business>().iter() {
+        for quota in &business.contract.quotas {
+            *demand.entry(quota.resource).or_default() += quota.amount_per_day;
+        }
+    }
+
+    (supply, demand)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::next_day;
+    use components::{Contract, Quota, Stockpile};
+
+    #[test]
+    fn prices_drift_towards_supply_and_demand() {
+        let mut game = Game::default();
+        let quota = Quota {
+            resource: Resource::Coal,
+            amount_per_day: 30,
+        };
+        let contract = Contract {
+            quotas: vec![quota],
+        };
+        let name = "Power station".into();
+        game.world.spawn((Business { name, contract },));
+        let deposit = Deposit::new(100, 100);
+        let stockpile = Stockpile::default();
+        game.world.spawn((deposit, Resource::Wood, stockpile));
+        market_system(&mut game);
+
+        // Nobody makes any coal, so it goes up a quarter of the way to its ceiling.
+        assert_eq!(game.market.price(Resource::Coal), 18);
+        // There's far too much wood, so it goes a quarter of the way down to its floor.
+        assert_eq!(game.market.price(Resource::Wood), 7);
+        // Nobody wants or makes boots.
+        let boots = base_price(Resource::Boots);
+        assert_eq!(game.market.price(Resource::Boots), boots);
+
+        // However long it goes on, prices stay within their limits.
+        for _ in 0..50 {
+            next_day(&mut game, market_system);
+        }
+        assert_eq!(game.market.price(Resource::Coal), 40);
+        assert_eq!(game.market.price(Resource::Wood), 2);
+    }
+
+    #[test]
+    fn spot_sales_count_towards_supply_for_a_day() {
+        let mut game = Game::default();
+        market_system(&mut game);
+        game.market.record_sale(Resource::Fish, 50);
+
+        next_day(&mut game, market_system);
+        let price = game.market.price(Resource::Fish);
+        assert!(price < base_price(Resource::Fish));
+        next_day(&mut game, market_system);
+        assert!(game.market.price(Resource::Fish) > price);
+    }
+
+    #[test]
+    fn goods_are_worth_more_the_further_and_faster_they_go() {
+        let market = Market::default();
+        let price = base_price(Resource::Coal);
+        assert_eq!(market.value(Resource::Coal, 10, 0., 0.), 10 * price);
+        assert_eq!(market.value(Resource::Coal, 10, 500., 1.), 20 * price);
+        assert!(market.value(Resource::Coal, 10, 500., 2.) < 20 * price);
+        let stale = market.value(Resource::Coal, 10, 0., 100.);
+        assert_eq!(stale, (10. * price as f32 * MIN_FRESHNESS).round() as Money);
+    }
+}
@@ -1,4 +1,14 @@
+pub mod contracts;
+pub mod finances;
+pub mod market;
+pub mod orders;
 mod physics;
+pub mod production;
+pub mod signalling;
+pub mod station;
+pub mod town;
+pub mod track;
+pub mod track_builder;
 pub mod train;
 pub mod transform_hierarchy;
 pub mod update_position;
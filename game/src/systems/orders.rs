@@ -0,0 +1,320 @@
+use super::{
+    station::{load, station_here, unload, Station},
+    train::Train,
+};
+use crate::{calendar::Calendar, GameEvent};
+use common::{
+    hecs::{self, Entity},
+    log,
+};
+use components::Resource;
+use std::collections::VecDeque;
+
+/// Something a train has been told to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Order {
+    /// Head for a station, and stop there.
+    GoTo(Entity),
+    /// Fill up with a resource from the station we're at, waiting for more to turn up if there
+    /// isn't enough.
+    LoadUntilFull(Resource),
+    /// Drop off everything we're carrying at the station we're at.
+    Unload,
+    /// Stay where we are until the clock next reads this time.
+    WaitUntil { hour: u32, minute: u32 },
+}
+
+/// A train's orders, which it works through one at a time.
+#[derive(Debug, Clone)]
+pub struct Orders {
+    pub list: Vec<Order>,
+    /// Index into `list` of the order we're carrying out.
+    pub current: usize,
+    /// Start again from the top once we've finished the last order.
+    pub repeat: bool,
+    started: bool,
+    /// The tick a `WaitUntil` order is up.
+    waiting_until: u64,
+    /// Set when the current order couldn't be carried out, to the layout it failed on. We don't
+    /// try it again until the track has changed.
+    failed: Option<u64>,
+}
+
+impl Default for Orders {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl Orders {
+    pub fn new(list: Vec<Order>) -> Self {
+        Self {
+            list,
+            current: 0,
+            repeat: true,
+            started: false,
+            waiting_until: 0,
+            failed: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn current(&self) -> Option<&Order> {
+        self.list.get(self.current)
+    }
+
+    /// Whether the train is stuck on an order it can't carry out.
+    pub fn has_failed(&self) -> bool {
+        self.failed.is_some()
+    }
+
+    fn advance(&mut self) {
+        self.current += 1;
+        if self.repeat && self.current >= self.list.len() {
+            self.current = 0;
+        }
+        self.started = false;
+    }
+}
+
+/// Carries out the train's current order, moving on to the next one once it's done. Trains
+/// only ever get through one order a tick.
+///
+/// An order that can't be carried out stops the train where it is, until `layout` (which changes
+/// whenever the track does) says it's worth another go.
+pub fn follow_orders(
+    world: &hecs::World,
+    calendar: &Calendar,
+    events: &mut VecDeque<GameEvent>,
+    layout: u64,
+    entity: Entity,
+    train: &mut Train,
+) {
+    match train.orders.failed {
+        Some(failed_on) if failed_on == layout => return,
+        Some(_) => {
+            train.orders.failed = None;
+            train.orders.started = false;
+        }
+        None => {}
+    }
+    let Some(order) = train.orders.current().cloned() else { return };
+    let started = std::mem::replace(&mut train.orders.started, true);
+
+    let done = match order {
+        Order::GoTo(station) => {
+            let Ok(segment) = world.get::<&Station>(station).map(|s| s.segment) else {
+                let reason = format!("was sent to {station:?}, which isn't a station");
+                fail(events, layout, entity, train, reason);
+                return;
+            };
+            if !started {
+                train.destination = Some(segment);
+                train.route.clear();
+            }
+            if train.destination.is_some() || train.dwell.is_some() {
+                false
+            } else if train.current_segment == segment {
+                true
+            } else {
+                // We stopped short, as there's no way to get there.
+                let name = world.get::<&Station>(station).unwrap().name.clone();
+                let reason = format!("can't get to {name}");
+                fail(events, layout, entity, train, reason);
+                return;
+            }
+        }
+        Order::LoadUntilFull(resource) => match station_here(world, train) {
+            Some(station_entity) => {
+                let mut station = world.get::<&mut Station>(station_entity).unwrap();
+                let now = calendar.ticks();
+                load(
+                    world,
+                    train,
+                    station_entity,
+                    &mut station,
+                    Some(resource),
+                    now,
+                )
+            }
+            None => {
+                let reason = "can't load, as it isn't at a station".into();
+                fail(events, layout, entity, train, reason);
+                return;
+            }
+        },
+        Order::Unload => {
+            let Some(station_entity) = station_here(world, train) else {
+                let reason = "can't unload, as it isn't at a station".into();
+                fail(events, layout, entity, train, reason);
+                return;
+            };
+            let station = world.get::<&Station>(station_entity).unwrap();
+            unload(world, events, entity, train, station_entity, &station);
+            true
+        }
+        Order::WaitUntil { hour, minute } => {
+            if !started {
+                train.orders.waiting_until = calendar.next_time(hour, minute);
+            }
+            calendar.ticks() >= train.orders.waiting_until
+        }
+    };
+
+    if done {
+        train.orders.advance();
+    }
+}
+
+/// Gives up on the train's current order until the track changes, letting the player know why.
+fn fail(
+    events: &mut VecDeque<GameEvent>,
+    layout: u64,
+    entity: Entity,
+    train: &mut Train,
+    reason: String,
+) {
+    log::warn!("{} {reason}", train.name);
+    train.orders.failed = Some(layout);
+    events.push_back(GameEvent::OrderFailed {
+        train: entity,
+        reason,
+    });
+}
+
+/// A short description of an order, for the player.
+pub fn describe_order(world: &hecs::World, order: &Order) -> String {
+    match order {
+        Order::GoTo(station) => match world.get::<&Station>(*station) {
+            Ok(station) => format!("Go to {}", station.name),
+            Err(_) => "Go nowhere".into(),
+        },
+        Order::LoadUntilFull(resource) => format!("Load {resource:?} until full"),
+        Order::Unload => "Unload".into(),
+        Order::WaitUntil { hour, minute } => format!("Wait until {hour:02}:{minute:02}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        spline::CubicBezier,
+        systems::{
+            track::{connect_segments, spawn_track_segment},
+            train::train_system,
+        },
+        test_helpers::station_with_train,
+        Game,
+    };
+    use common::glam::Vec3;
+    use components::Transform;
+
+    /// Has the train look at its orders once, and says which one it's on afterwards.
+    fn follow(game: &mut Game, entity: Entity, layout: u64) -> usize {
+        let (world, calendar, events) = (&game.world, &game.calendar, &mut game.events);
+        let mut train = world.get::<&mut Train>(entity).unwrap();
+        follow_orders(world, calendar, events, layout, entity, &mut train);
+        train.orders.current
+    }
+
+    fn give_orders(game: &mut Game, entity: Entity, orders: Orders) {
+        game.world.get::<&mut Train>(entity).unwrap().orders = orders;
+    }
+
+    #[test]
+    fn orders_go_round_and_round() {
+        let mut game = Game::default();
+        let (train, station) = station_with_train(&mut game, Vec::new(), Vec::new());
+        let orders = vec![Order::GoTo(station), Order::Unload];
+        give_orders(&mut game, train, Orders::new(orders.clone()));
+
+        // Going somewhere takes until the train has stopped there.
+        assert_eq!(follow(&mut game, train, 0), 0);
+        let segment = game.world.get::<&Station>(station).unwrap().segment;
+        let destination = game.world.get::<&Train>(train).unwrap().destination;
+        assert_eq!(destination, Some(segment));
+        assert_eq!(follow(&mut game, train, 0), 0);
+        game.world.get::<&mut Train>(train).unwrap().destination = None;
+        assert_eq!(follow(&mut game, train, 0), 1);
+
+        // One order a tick, and then back to the top.
+        assert_eq!(follow(&mut game, train, 0), 0);
+
+        let mut once = Orders::new(orders);
+        once.repeat = false;
+        once.current = 1;
+        give_orders(&mut game, train, once);
+        assert_eq!(follow(&mut game, train, 0), 2);
+        assert_eq!(follow(&mut game, train, 0), 2);
+    }
+
+    #[test]
+    fn waiting_lasts_until_the_clock_says_so() {
+        let mut game = Game::default();
+        let (train, _) = station_with_train(&mut game, Vec::new(), Vec::new());
+        let orders = vec![Order::WaitUntil { hour: 7, minute: 0 }, Order::Unload];
+        give_orders(&mut game, train, Orders::new(orders));
+
+        let until = game.calendar.next_time(7, 0);
+        assert_eq!(follow(&mut game, train, 0), 0);
+        while game.calendar.ticks() + 1 < until {
+            game.calendar.tick();
+        }
+        assert_eq!(follow(&mut game, train, 0), 0);
+        game.calendar.tick();
+        assert_eq!(follow(&mut game, train, 0), 1);
+    }
+
+    #[test]
+    fn trains_that_cant_get_somewhere_wait_for_the_track_to_change() {
+        let mut game = Game::default();
+        let (train, station) = station_with_train(&mut game, Vec::new(), Vec::new());
+        let here = game.world.get::<&Station>(station).unwrap().segment;
+        let curve = CubicBezier::straight(Vec3::X * 10., Vec3::X * 20.);
+        let there = spawn_track_segment(&mut game.world, curve);
+        let far_station = game.world.spawn((
+            Station {
+                name: "Far away".into(),
+                segment: there,
+                dwell_time: 0.,
+                catchment_radius: 0.,
+                catchment: Vec::new(),
+                supply: Default::default(),
+            },
+            Transform::default(),
+        ));
+        let orders = Orders::new(vec![Order::GoTo(far_station)]);
+        give_orders(&mut game, train, orders);
+
+        // The driver finds there's no way there, and the order fails.
+        train_system(&mut game);
+        train_system(&mut game);
+        let failed = |game: &Game| game.world.get::<&Train>(train).unwrap().orders.has_failed();
+        assert!(failed(&game));
+        let failures = game
+            .events
+            .iter()
+            .filter(|event| matches!(event, GameEvent::OrderFailed { .. }))
+            .count();
+        assert_eq!(failures, 1);
+
+        // Nothing more happens until the track changes.
+        game.events.clear();
+        for _ in 0..10 {
+            train_system(&mut game);
+        }
+        assert!(failed(&game));
+        assert!(game.events.is_empty());
+
+        connect_segments(&mut game.world, here, there);
+        game.signalling.layout_changed();
+        train_system(&mut game);
+        assert!(!failed(&game));
+        let destination = game.world.get::<&Train>(train).unwrap().destination;
+        assert_eq!(destination, Some(there));
+    }
+}
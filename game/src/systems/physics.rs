@@ -65,7 +65,7 @@ impl PhysicsContext {
             &self.rigid_body_set,
             &self.collider_set,
             ray,
-            100.,
+            Real::MAX,
             true,
             Default::default(),
         ) else { return None };
@@ -75,6 +75,15 @@ impl PhysicsContext {
         hecs::Entity::from_bits(self.collider_set.get(handle).unwrap().user_data as _)
     }
 
+    pub(crate) fn remove_collider(&mut self, handle: ColliderHandle) {
+        self.collider_set.remove(
+            handle,
+            &mut self.island_manager,
+            &mut self.rigid_body_set,
+            false,
+        );
+    }
+
     fn render_debug(&mut self, backend: &mut PhysicsRenderer) {
         self.debug.render(
             backend,
@@ -169,7 +178,7 @@ fn create_missing_collider_handles(game: &mut Game) {
         .without::<&ColliderHandle>()
         .iter()
     {
-        let (y_offset, shape) = get_shape_from_model(model);
+        let (y_offset, shape) = get_shape_from_model(model, transform.scale);
         let mut collider_transform = transform.clone();
         collider_transform.position.y += y_offset;
         collider_info.y_offset = y_offset;
@@ -238,7 +247,8 @@ where
     }
 }
 
-fn get_shape_from_model(model: &GLTFModel) -> (f32, SharedShape) {
+/// A box around the model, as big as it's drawn.
+fn get_shape_from_model(model: &GLTFModel, scale: glam::Vec3) -> (f32, SharedShape) {
     let mut max_x = f32::NEG_INFINITY;
     let mut min_x = f32::INFINITY;
     let mut max_y = f32::NEG_INFINITY;
@@ -258,9 +268,9 @@ fn get_shape_from_model(model: &GLTFModel) -> (f32, SharedShape) {
         }
     }
 
-    let half_x = (max_x - min_x) / 2.;
-    let half_y = (max_y - min_y) / 2.;
-    let half_z = (max_z - min_z) / 2.;
+    let half_x = (max_x - min_x) / 2. * scale.x;
+    let half_y = (max_y - min_y) / 2. * scale.y;
+    let half_z = (max_z - min_z) / 2. * scale.z;
 
     (half_y, SharedShape::cuboid(half_x, half_y, half_z))
 }
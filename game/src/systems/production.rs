@@ -0,0 +1,202 @@
+use crate::Game;
+use common::hecs::{self, Entity};
+use components::{Recipe, Resource, Stockpile};
+
+/// Something in the ground (or the sea) that gives up a resource over time. Lives alongside the
+/// deposit's `Resource` and a `Stockpile` that the output piles up in.
+#[derive(Debug, Clone)]
+pub struct Deposit {
+    /// Units produced per in-game day.
+    pub rate: u64,
+    /// Production stops once the stockpile holds this many units, until someone takes some away.
+    pub cap: usize,
+    /// Production towards the next unit, in units * ticks. We make a unit every time this reaches
+    /// a day's worth of ticks, which keeps the output exact however the rate divides the day.
+    progress: u64,
+}
+
+impl Deposit {
+    pub fn new(rate: u64, cap: usize) -> Self {
+        Self {
+            rate,
+            cap,
+            progress: 0,
+        }
+    }
+
+    /// Works the deposit for one tick, returning how many units came out.
+    fn tick(&mut self, stockpiled: usize, ticks_per_day: u64) -> usize {
+        if stockpiled >= self.cap {
+            self.progress = 0;
+            return 0;
+        }
+
+        self.progress += self.rate;
+        let produced = (self.progress / ticks_per_day) as usize;
+        self.progress %= ticks_per_day;
+        produced.min(self.cap - stockpiled)
+    }
+}
+
+/// A business working through a recipe. Deliveries go into `inputs`, and whatever it makes piles
+/// up in `outputs` for a station to collect.
+#[derive(Debug, Clone)]
+pub struct Workshop {
+    pub recipe: Recipe,
+    pub inputs: Stockpile,
+    pub outputs: Stockpile,
+    /// Work stops once this many units are waiting to be collected.
+    pub cap: usize,
+    /// Works the same way as [`Deposit::progress`], but counts batches rather than units.
+    progress: u64,
+}
+
+impl Workshop {
+    pub fn new(recipe: Recipe, cap: usize) -> Self {
+        Self {
+            recipe,
+            inputs: Default::default(),
+            outputs: Default::default(),
+            cap,
+            progress: 0,
+        }
+    }
+
+    /// Works for one tick, making a batch whenever one's due and there's stock to make it from.
+    fn tick(&mut self, ticks_per_day: u64) {
+        self.progress = (self.progress + self.recipe.batches_per_day).min(ticks_per_day);
+        if self.progress < ticks_per_day {
+            return;
+        }
+
+        let recipe = &self.recipe;
+        let has_inputs = recipe
+            .inputs
+            .iter()
+            .all(|input| self.inputs.amount(input.resource) >= input.amount);
+        let batch_size: usize = recipe.outputs.iter().map(|output| output.amount).sum();
+        if !has_inputs || self.outputs.total() + batch_size > self.cap {
+            return;
+        }
+
+        for input in &recipe.inputs {
+            self.inputs.take(input.resource, input.amount);
+        }
+        for output in &recipe.outputs {
+            self.outputs.add(output.resource, output.amount);
+        }
+        self.progress -= ticks_per_day;
+    }
+}
+
+/// Every deposit and workshop produces a little more each tick of the calendar.
+pub fn production_system(game: &mut Game) {
+    let ticks_per_day = game.calendar.ticks_per_day();
+    for (_, (deposit, resource, stockpile)) in game
+        .world
+        .query_mut::<(&mut Deposit, &Resource, &mut Stockpile)>()
+    {
+        let produced = deposit.tick(stockpile.total(), ticks_per_day);
+        stockpile.add(*resource, produced);
+    }
+
+    for (_, workshop) in game.world.query_mut::<&mut Workshop>() {
+        workshop.tick(ticks_per_day);
+    }
+}
+
+/// Moves everything waiting at the deposits and workshops in `catchment` over to `into`.
+pub fn collect_output(world: &hecs::World, catchment: &[Entity], into: &mut Stockpile) {
+    for entity in catchment {
+        if world.get::<&Deposit>(*entity).is_ok() {
+            if let Ok(mut stockpile) = world.get::<&mut Stockpile>(*entity) {
+                move_all(&mut stockpile, into);
+            }
+        }
+        if let Ok(mut workshop) = world.get::<&mut Workshop>(*entity) {
+            move_all(&mut workshop.outputs, into);
+        }
+    }
+}
+
+fn move_all(from: &mut Stockpile, to: &mut Stockpile) {
+    for (resource, amount) in from.take_all() {
+        to.add(resource, amount);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use components::Ingredient;
+
+    fn run_for_a_day(game: &mut Game) {
+        for _ in 0..game.calendar.ticks_per_day() {
+            production_system(game);
+        }
+    }
+
+    #[test]
+    fn deposits_produce_their_daily_rate_until_they_fill_up() {
+        let mut game = Game::default();
+        let deposit = Deposit::new(30, 50);
+        let entity = game
+            .world
+            .spawn((deposit, Resource::Coal, Stockpile::default()));
+
+        run_for_a_day(&mut game);
+        assert_eq!(game.get::<Stockpile>(entity).amount(Resource::Coal), 30);
+        run_for_a_day(&mut game);
+        assert_eq!(game.get::<Stockpile>(entity).amount(Resource::Coal), 50);
+
+        let mut collected = Stockpile::default();
+        collect_output(&game.world, &[entity], &mut collected);
+        assert_eq!(collected.amount(Resource::Coal), 50);
+        assert!(game.get::<Stockpile>(entity).is_empty());
+    }
+
+    #[test]
+    fn workshops_use_up_their_inputs_until_they_fill_up() {
+        let mut game = Game::default();
+        let recipe = Recipe {
+            name: "Baking".into(),
+            inputs: vec![Ingredient {
+                resource: Resource::Wood,
+                amount: 2,
+            }],
+            outputs: vec![Ingredient {
+                resource: Resource::Bread,
+                amount: 3,
+            }],
+            batches_per_day: 4,
+        };
+        let mut workshop = Workshop::new(recipe, 9);
+        workshop.inputs.add(Resource::Wood, 5);
+        let entity = game.world.spawn((workshop,));
+
+        // There's only enough wood for two batches.
+        run_for_a_day(&mut game);
+        {
+            let workshop = game.get::<Workshop>(entity);
+            assert_eq!(workshop.outputs.amount(Resource::Bread), 6);
+            assert_eq!(workshop.inputs.amount(Resource::Wood), 1);
+        }
+
+        // Now there's plenty, but only room for one more.
+        game.get::<Workshop>(entity).inputs.add(Resource::Wood, 10);
+        run_for_a_day(&mut game);
+        {
+            let workshop = game.get::<Workshop>(entity);
+            assert_eq!(workshop.outputs.amount(Resource::Bread), 9);
+            assert_eq!(workshop.inputs.amount(Resource::Wood), 9);
+        }
+
+        let mut collected = Stockpile::default();
+        collect_output(&game.world, &[entity], &mut collected);
+        assert_eq!(collected.amount(Resource::Bread), 9);
+        run_for_a_day(&mut game);
+        let workshop = game.get::<Workshop>(entity);
+        assert!(workshop.outputs.amount(Resource::Bread) > 0);
+        assert!(workshop.inputs.amount(Resource::Wood) < 9);
+    }
+}
@@ -0,0 +1,305 @@
+use super::{
+    track::{exits, next_segment, Direction, Junction, TrackSegment},
+    train::Train,
+};
+use crate::{Game, GameEvent};
+use common::{
+    glam::Vec3,
+    hecs::{self, Entity},
+    log,
+};
+use components::{GLTFAsset, MaterialOverrides, Transform};
+use std::collections::{HashMap, HashSet};
+
+/// A signal protecting the block beyond the end of `segment` from trains heading in `direction`.
+/// Blocks never extend past a signal, whichever way it faces. Trains don't look at signals at
+/// all; they go by their reservations, and the signal just shows the driver's view of them.
+#[derive(Debug, Clone)]
+pub struct Signal {
+    pub segment: Entity,
+    pub direction: Direction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aspect {
+    Danger,
+    Clear,
+}
+
+/// Identifies a block by the lowest numbered segment in it, so it stays the same from one tick
+/// to the next for as long as the layout doesn't change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockId(Entity);
+
+/// Keeps track of which trains are in, or are allowed into, each block of track.
+#[derive(Debug, Default)]
+pub struct Signalling {
+    blocks: HashMap<Entity, BlockId>,
+    /// Whether `blocks` is up to date with the track and signals. Working the blocks out means
+    /// going over the whole network, so we only do it when something's been built.
+    blocks_built: bool,
+    /// Goes up by one every time the layout changes.
+    layout: u64,
+    occupied: HashMap<BlockId, HashSet<Entity>>,
+    reservations: HashMap<BlockId, Entity>,
+    /// Reservations that were asked for again since we last checked. Anything else is let go.
+    renewed: HashSet<(BlockId, Entity)>,
+    /// Trains that were refused a reservation, and the train that's in their way.
+    waiting_on: HashMap<Entity, Entity>,
+    deadlocked: Vec<Entity>,
+}
+
+impl Signalling {
+    /// Lets us know the track or the signals have changed, so the blocks need working out again.
+    pub fn layout_changed(&mut self) {
+        self.blocks_built = false;
+        self.layout += 1;
+    }
+
+    /// Tells one layout of the track from another, so anything that depends on it can tell when
+    /// it's changed.
+    pub fn layout(&self) -> u64 {
+        self.layout
+    }
+
+    pub fn block_of(&self, segment: Entity) -> Option<BlockId> {
+        self.blocks.get(&segment).copied()
+    }
+
+    /// Is there a train in this block, or on its way into it?
+    pub fn is_claimed(&self, block: BlockId) -> bool {
+        self.occupied.contains_key(&block) || self.reservations.contains_key(&block)
+    }
+
+    /// Asks for permission for `train` to enter `block`, which is only granted if no other train
+    /// is in it or has already been given permission. Trains need to keep asking every tick for
+    /// as long as they still need it.
+    pub fn reserve(&mut self, train: Entity, block: BlockId) -> bool {
+        let occupant = self
+            .occupied
+            .get(&block)
+            .and_then(|trains| trains.iter().filter(|t| **t != train).min());
+        let holder = occupant
+            .or_else(|| self.reservations.get(&block).filter(|t| **t != train))
+            .copied();
+
+        if let Some(holder) = holder {
+            self.waiting_on.insert(train, holder);
+            return false;
+        }
+
+        self.reservations.insert(block, train);
+        self.renewed.insert((block, train));
+        true
+    }
+
+    /// Works out which segments belong to which block. Junctions always get a block to
+    /// themselves, so a train has to reserve its path over the points before it can pass them.
+    /// Nothing's done unless the layout has changed since last time.
+    fn rebuild_blocks(&mut self, world: &hecs::World) {
+        if self.blocks_built {
+            return;
+        }
+        self.blocks_built = true;
+
+        let boundaries: HashSet<(Entity, Direction)> = world
+            .query::<&Signal>()
+            .iter()
+            .map(|(_, signal)| (signal.segment, signal.direction))
+            .collect();
+        let is_junction = |segment: Entity| world.get::<&Junction>(segment).is_ok();
+
+        self.blocks.clear();
+        let segments: Vec<Entity> = world
+            .query::<&TrackSegment>()
+            .iter()
+            .map(|(entity, _)| entity)
+            .collect();
+
+        for start in segments {
+            if self.blocks.contains_key(&start) {
+                continue;
+            }
+
+            let mut members = HashSet::from([start]);
+            let mut to_visit = vec![start];
+            while let Some(segment) = to_visit.pop() {
+                if is_junction(segment) {
+                    continue;
+                }
+
+                for direction in [Direction::AToB, Direction::BToA] {
+                    if boundaries.contains(&(segment, direction)) {
+                        continue;
+                    }
+                    for (next, next_direction) in exits(world, segment, direction) {
+                        // A signal facing the other way sits on the same bit of track.
+                        let facing_back = boundaries.contains(&(next, next_direction.reversed()));
+                        if facing_back || is_junction(next) || !members.insert(next) {
+                            continue;
+                        }
+                        to_visit.push(next);
+                    }
+                }
+            }
+
+            let id = BlockId(*members.iter().min().unwrap());
+            for member in members {
+                self.blocks.insert(member, id);
+            }
+        }
+    }
+
+    fn update_occupancy(&mut self, world: &hecs::World) {
+        self.occupied.clear();
+        for (entity, train) in world.query::<&Train>().iter() {
+            for (segment, _) in train.segments() {
+                let Some(block) = self.block_of(segment) else { continue };
+                self.occupied.entry(block).or_default().insert(entity);
+            }
+        }
+    }
+
+    /// Lets go of any reservation that its train has stopped asking for and isn't sitting in.
+    fn release_reservations(&mut self, world: &hecs::World) {
+        let renewed = std::mem::take(&mut self.renewed);
+        let occupied = &self.occupied;
+        self.reservations.retain(|block, train| {
+            world.contains(*train)
+                && (renewed.contains(&(*block, *train))
+                    || occupied.get(block).map_or(false, |t| t.contains(train)))
+        });
+    }
+
+    /// Follows the chain of trains waiting on each other, looking for one that loops back on
+    /// itself.
+    fn find_deadlock(&self) -> Vec<Entity> {
+        for start in self.waiting_on.keys() {
+            let mut chain = vec![*start];
+            let mut current = *start;
+            while let Some(next) = self.waiting_on.get(&current) {
+                if let Some(index) = chain.iter().position(|t| t == next) {
+                    let mut cycle = chain.split_off(index);
+                    cycle.sort();
+                    return cycle;
+                }
+                chain.push(*next);
+                current = *next;
+            }
+        }
+
+        Vec::new()
+    }
+}
+
+pub fn spawn_signal(world: &mut hecs::World, segment: Entity, direction: Direction) -> Entity {
+    let (position, heading) = {
+        let track_segment = world.get::<&TrackSegment>(segment).unwrap();
+        let length = track_segment.length();
+        track_segment.point_along(direction, length)
+    };
+
+    // Stand the signal beside the track, on the driver's side.
+    let beside = heading.cross(Vec3::Y).normalize_or_zero() * -0.5;
+    world.spawn((
+        Signal { segment, direction },
+        Transform {
+            position: position + beside + Vec3::Y * 0.5,
+            scale: Vec3::splat(0.2),
+            ..Default::default()
+        },
+        GLTFAsset::new("cube.glb"),
+        aspect_colour(Aspect::Danger),
+    ))
+}
+
+pub fn signalling_system(game: &mut Game) {
+    let world = &game.world;
+    let signalling = &mut game.signalling;
+
+    signalling.rebuild_blocks(world);
+    signalling.update_occupancy(world);
+    signalling.release_reservations(world);
+
+    // Trains ask for their reservations during `train_system`, so this is last tick's news.
+    let deadlocked = signalling.find_deadlock();
+    if !deadlocked.is_empty() && deadlocked != signalling.deadlocked {
+        log::warn!("Trains {deadlocked:?} are deadlocked!");
+        game.events
+            .push_back(GameEvent::Deadlock(deadlocked.clone()));
+    }
+    signalling.deadlocked = deadlocked;
+    signalling.waiting_on.clear();
+
+    for (_, (signal, colour)) in world.query::<(&Signal, &mut MaterialOverrides)>().iter() {
+        *colour = aspect_colour(signal_aspect(world, signalling, signal));
+    }
+}
+
+/// A signal shows clear if nobody is beyond it, or if the only train that's been let through is
+/// the one waiting at it.
+fn signal_aspect(world: &hecs::World, signalling: &Signalling, signal: &Signal) -> Aspect {
+    let Some(approach) = signalling.block_of(signal.segment) else { return Aspect::Danger };
+    let Some((next, _)) = next_segment(world, signal.segment, signal.direction) else {
+        return Aspect::Danger;
+    };
+    let Some(beyond) = signalling.block_of(next) else { return Aspect::Danger };
+
+    if signalling.occupied.contains_key(&beyond) {
+        return Aspect::Danger;
+    }
+    match signalling.reservations.get(&beyond) {
+        None => Aspect::Clear,
+        Some(train) => {
+            let approaching = signalling
+                .occupied
+                .get(&approach)
+                .map_or(false, |trains| trains.contains(train));
+            if approaching {
+                Aspect::Clear
+            } else {
+                Aspect::Danger
+            }
+        }
+    }
+}
+
+fn aspect_colour(aspect: Aspect) -> MaterialOverrides {
+    let colour = match aspect {
+        Aspect::Danger => [1., 0., 0., 1.],
+        Aspect::Clear => [0., 1., 0., 1.],
+    };
+    MaterialOverrides {
+        base_colour_factor: colour.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        spline::CubicBezier,
+        systems::track::{connect_segments, spawn_track_segment},
+    };
+
+    #[test]
+    fn blocks_are_only_rebuilt_when_the_layout_changes() {
+        let mut world = hecs::World::new();
+        let a = spawn_track_segment(&mut world, CubicBezier::straight(Vec3::ZERO, Vec3::X));
+        let b = spawn_track_segment(&mut world, CubicBezier::straight(Vec3::X, Vec3::X * 2.));
+        connect_segments(&mut world, a, b);
+
+        let mut signalling = Signalling::default();
+        signalling.rebuild_blocks(&world);
+        assert_eq!(signalling.block_of(a), signalling.block_of(b));
+
+        spawn_signal(&mut world, a, Direction::AToB);
+        signalling.rebuild_blocks(&world);
+        assert_eq!(signalling.block_of(a), signalling.block_of(b));
+
+        signalling.layout_changed();
+        signalling.rebuild_blocks(&world);
+        assert_ne!(signalling.block_of(a), signalling.block_of(b));
+        assert!(signalling.block_of(b).is_some());
+    }
+}
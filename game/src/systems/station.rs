@@ -0,0 +1,337 @@
+use super::{
+    production::collect_output,
+    track::{Direction, TrackSegment},
+    train::{Cargo, Train},
+};
+use crate::{placement::Footprint, Game, GameEvent};
+use common::{
+    glam::Vec3,
+    hecs::{self, Entity},
+    log,
+};
+use components::{Business, GLTFAsset, Resource, Stockpile, Transform};
+use std::collections::VecDeque;
+
+/// How far (in metres) a station reaches out to the businesses and deposits around it.
+pub const DEFAULT_CATCHMENT_RADIUS: f32 = 60.;
+/// How long (in seconds) trains stop for, once they've finished loading and unloading.
+pub const DEFAULT_DWELL_TIME: f32 = 5.;
+/// How much room (in metres, from the middle) the station building takes up. It's beside the
+/// track rather than on it, so this has to be less than the distance between the two.
+const STATION_RADIUS: f32 = 0.75;
+
+/// Somewhere beside the track for trains to stop, load and unload.
+#[derive(Debug, Clone)]
+pub struct Station {
+    pub name: String,
+    /// The segment the platform runs alongside. Trains stop at the far end of it.
+    pub segment: Entity,
+    pub dwell_time: f32,
+    pub catchment_radius: f32,
+    /// The businesses and resource deposits close enough to use the station.
+    pub catchment: Vec<Entity>,
+    /// Goods waiting on the platform for a train to pick them up.
+    pub supply: Stockpile,
+}
+
+/// A train stopped at a station.
+#[derive(Debug, Clone, Copy)]
+pub struct Dwell {
+    pub station: Entity,
+    /// Seconds left until the train can leave.
+    pub remaining: f32,
+    /// Whether we've done the loading and unloading yet.
+    pub worked: bool,
+}
+
+/// Where and when a train picked up the goods it's carrying.
+#[derive(Debug, Clone, Copy)]
+pub struct Consignment {
+    pub station: Entity,
+    /// The calendar tick the first of the goods went on board.
+    pub loaded_at: u64,
+}
+
+/// Builds a station beside `segment`, taking in everything within `catchment_radius` of it.
+pub fn spawn_station<S: Into<String>>(
+    world: &mut hecs::World,
+    name: S,
+    segment: Entity,
+    catchment_radius: f32,
+) -> Entity {
+    let (position, heading) = {
+        let track_segment = world.get::<&TrackSegment>(segment).unwrap();
+        let half_way = track_segment.length() / 2.;
+        track_segment.point_along(Direction::AToB, half_way)
+    };
+
+    // The platform goes on the left, away from the signals.
+    let beside = heading.cross(Vec3::Y).normalize_or_zero() * 1.5;
+    let position = position + beside;
+    let catchment = catchment(world, position, catchment_radius);
+
+    world.spawn((
+        Station {
+            name: name.into(),
+            segment,
+            dwell_time: DEFAULT_DWELL_TIME,
+            catchment_radius,
+            catchment,
+            supply: Default::default(),
+        },
+        Transform {
+            position,
+            scale: Vec3::splat(0.5),
+            ..Default::default()
+        },
+        GLTFAsset::new("building.glb"),
+        Footprint::around(position, STATION_RADIUS),
+    ))
+}
+
+/// Works out again what each station serves, for when things have been put down around stations
+/// that were already there.
+pub fn update_catchments(world: &mut hecs::World) {
+    let catchments: Vec<(Entity, Vec<Entity>)> = world
+        .query::<(&Station, &Transform)>()
+        .iter()
+        .map(|(entity, (station, transform))| {
+            let radius = station.catchment_radius;
+            (entity, catchment(world, transform.position, radius))
+        })
+        .collect();
+    for (entity, catchment) in catchments {
+        world.get::<&mut Station>(entity).unwrap().catchment = catchment;
+    }
+}
+
+/// Everything a station at `position` would serve.
+fn catchment(world: &hecs::World, position: Vec3, radius: f32) -> Vec<Entity> {
+    let businesses = world.query::<(&Business, &Transform)>();
+    let deposits = world.query::<(&Resource, &Transform)>();
+    let mut covered: Vec<Entity> = businesses
+        .iter()
+        .map(|(entity, (_, transform))| (entity, transform.position))
+        .chain(
+            deposits
+                .iter()
+                .map(|(entity, (_, transform))| (entity, transform.position)),
+        )
+        .filter(|(_, p)| p.distance(position) <= radius)
+        .map(|(entity, _)| entity)
+        .collect();
+    // Towns are both, so they'll turn up twice.
+    covered.sort();
+    covered.dedup();
+    covered
+}
+
+/// If there's a station on `segment`, how long a train pulling in there should stop for.
+pub fn dwell_at(world: &hecs::World, segment: Entity) -> Option<Dwell> {
+    world
+        .query::<&Station>()
+        .iter()
+        .find(|(_, station)| station.segment == segment)
+        .map(|(entity, station)| Dwell {
+            station: entity,
+            remaining: station.dwell_time,
+            worked: false,
+        })
+}
+
+/// Works every train stopped at a station, then sees out the rest of its dwell time before
+/// letting it leave. Trains without orders unload whatever they're carrying and fill up with
+/// whatever's waiting on the platform; anyone with orders does as they say instead.
+pub fn station_system(game: &mut Game) {
+    let dt = game.time.delta();
+    let now = game.calendar.ticks();
+    let world = &game.world;
+
+    for (entity, train) in world.query::<&mut Train>().iter() {
+        let Some(mut dwell) = train.dwell else { continue };
+        let Ok(mut station) = world.get::<&mut Station>(dwell.station) else {
+            train.dwell = None;
+            continue;
+        };
+
+        if !dwell.worked {
+            if train.orders.is_empty() {
+                let events = &mut game.events;
+                unload(world, events, entity, train, dwell.station, &station);
+                load(world, train, dwell.station, &mut station, None, now);
+            }
+            dwell.worked = true;
+        }
+
+        dwell.remaining -= dt;
+        if dwell.remaining <= 0. {
+            log::info!("{} departs from {}", train.name, station.name);
+            train.dwell = None;
+        } else {
+            train.dwell = Some(dwell);
+        }
+    }
+}
+
+/// The station the train is stood at, if any.
+pub fn station_here(world: &hecs::World, train: &Train) -> Option<Entity> {
+    if train.destination.is_some() {
+        return None;
+    }
+    world
+        .query::<&Station>()
+        .iter()
+        .find(|(_, station)| station.segment == train.current_segment)
+        .map(|(entity, _)| entity)
+}
+
+/// Empties every wagon in the train at `station`, and lets everyone know what was delivered.
+pub fn unload(
+    world: &hecs::World,
+    events: &mut VecDeque<GameEvent>,
+    train_entity: Entity,
+    train: &mut Train,
+    station_entity: Entity,
+    station: &Station,
+) {
+    let mut unloaded = Stockpile::default();
+    for car in &train.cars {
+        let Ok(mut cargo) = world.get::<&mut Cargo>(*car) else { continue };
+        cargo.unload_all(&mut unloaded);
+    }
+
+    for (resource, amount) in unloaded.iter() {
+        log::info!(
+            "{} delivered {amount} {resource:?} to {}",
+            train.name,
+            station.name
+        );
+        events.push_back(GameEvent::Delivery {
+            train: train_entity,
+            station: station_entity,
+            resource,
+            amount,
+            consignment: train.consignment,
+        });
+    }
+    train.consignment = None;
+}
+
+/// Fills up the train's wagons from the platform, with just `only` if it's given. Anything
+/// waiting at the deposits and workshops the station serves gets brought over to the platform
+/// first.
+///
+/// Returns `true` once there's no more room in any wagon that could take it.
+pub fn load(
+    world: &hecs::World,
+    train: &mut Train,
+    station_entity: Entity,
+    station: &mut Station,
+    only: Option<Resource>,
+    now: u64,
+) -> bool {
+    collect_output(world, &station.catchment, &mut station.supply);
+    let supply = &mut station.supply;
+
+    let mut full = true;
+    for car in &train.cars {
+        let Ok(mut cargo) = world.get::<&mut Cargo>(*car) else { continue };
+        if only.map_or(false, |only| !cargo.accepts(only)) {
+            continue;
+        }
+
+        let available: Vec<Resource> = supply
+            .iter()
+            .map(|(resource, _)| resource)
+            .filter(|resource| only.map_or(true, |only| only == *resource))
+            .collect();
+        for resource in available {
+            if cargo.load(supply, resource, usize::MAX) > 0 && train.consignment.is_none() {
+                train.consignment = Some(Consignment {
+                    station: station_entity,
+                    loaded_at: now,
+                });
+            }
+        }
+        full &= cargo.is_full();
+    }
+    full
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        systems::{
+            production::Deposit,
+            train::{train_system, WAGON_CAPACITY},
+        },
+        test_helpers::station_with_train,
+        time::UPDATE_RATE,
+    };
+
+    fn step(game: &mut Game) {
+        train_system(game);
+        station_system(game);
+    }
+
+    #[test]
+    fn trains_dwell_at_stations_and_swap_their_cargo() {
+        let mut game = Game::default();
+        let mut coal = Stockpile::default();
+        coal.add(Resource::Coal, 5);
+        let mine = game
+            .world
+            .spawn((Deposit::new(0, 50), Resource::Coal, coal));
+
+        let mut fish = Stockpile::default();
+        fish.add(Resource::Fish, 8);
+        let mut fish_wagon = Cargo::new(vec![Resource::Fish], WAGON_CAPACITY);
+        fish_wagon.load(&mut fish, Resource::Fish, 8);
+        let coal_wagon = Cargo::new(vec![Resource::Coal], WAGON_CAPACITY);
+        let wagons = vec![fish_wagon, coal_wagon];
+        let (train, station) = station_with_train(&mut game, vec![mine], wagons);
+        game.world.get::<&mut Station>(station).unwrap().dwell_time = 2.;
+        let segment = game.world.get::<&Station>(station).unwrap().segment;
+        game.world.get::<&mut Train>(train).unwrap().destination = Some(segment);
+
+        // Run down the platform until the train pulls up.
+        let mut ticks = 0;
+        while game.world.get::<&Train>(train).unwrap().dwell.is_none() {
+            step(&mut game);
+            ticks += 1;
+            assert!(ticks < 1_000, "the train never got to the station");
+        }
+        let delivered: Vec<(Resource, usize)> = game
+            .events
+            .drain(..)
+            .filter_map(|event| match event {
+                GameEvent::Delivery {
+                    resource, amount, ..
+                } => Some((resource, amount)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(delivered, vec![(Resource::Fish, 8)]);
+
+        let cars = game.world.get::<&Train>(train).unwrap().cars.clone();
+        let carried = |resource| {
+            cars.iter()
+                .filter_map(|car| game.world.get::<&Cargo>(*car).ok())
+                .map(|cargo| cargo.contents().amount(resource))
+                .sum::<usize>()
+        };
+        assert_eq!(carried(Resource::Fish), 0);
+        assert_eq!(carried(Resource::Coal), 5);
+
+        // It stays put for the station's dwell time, and then it's free to go.
+        let mut dwelt = UPDATE_RATE;
+        while game.world.get::<&Train>(train).unwrap().dwell.is_some() {
+            step(&mut game);
+            dwelt += UPDATE_RATE;
+            assert!(dwelt < 10., "the train never left");
+        }
+        let error = (dwelt - 2.).abs();
+        assert!(error < UPDATE_RATE * 1.5, "dwelt for {dwelt}s");
+    }
+}
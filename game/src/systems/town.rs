@@ -0,0 +1,208 @@
+use super::production::Deposit;
+use crate::{placement::Footprint, Game};
+use common::{glam::Vec3, hecs, log};
+use components::{Business, Contract, GLTFAsset, Quota, Resource, Stockpile, Transform};
+
+/// What the people of every town want delivered.
+pub const CONSUMER_GOODS: [Resource; 3] = [Resource::Bread, Resource::Fish, Resource::Boots];
+
+/// How many residents it takes to make a passenger a day, or to want a passenger a day from
+/// somewhere else.
+const RESIDENTS_PER_PASSENGER: u32 = 20;
+/// How many residents it takes to want a unit of each consumer good a day.
+const RESIDENTS_PER_GOODS_UNIT: u32 = 25;
+/// Passengers stop turning up at the station once this many are waiting.
+const PASSENGER_CAP: usize = 200;
+
+/// Towns that get at least this share of what they wanted grow, and those that get less than
+/// `POORLY_SERVED` shrink.
+const WELL_SERVED: f32 = 0.75;
+const POORLY_SERVED: f32 = 0.25;
+/// How much a town grows or shrinks by in a day, as a share of its population.
+const GROWTH_RATE: f32 = 0.05;
+const SHRINK_RATE: f32 = 0.03;
+const MIN_POPULATION: u32 = 50;
+/// How much room (in metres, from the middle) a town takes up, however big it grows.
+pub const TOWN_RADIUS: f32 = 10.;
+
+/// Somewhere people live. Towns want consumer goods and visitors from other towns, which they
+/// ask for through their `Business` contract, and send their own passengers out through a
+/// `Deposit`.
+#[derive(Debug, Clone)]
+pub struct Town {
+    pub population: u32,
+    /// What's been delivered today.
+    received: Stockpile,
+    /// The day `received` is counting towards.
+    day: u64,
+}
+
+impl Town {
+    fn demand(&self) -> Vec<Quota> {
+        let goods = (self.population / RESIDENTS_PER_GOODS_UNIT) as usize;
+        CONSUMER_GOODS
+            .iter()
+            .map(|resource| Quota {
+                resource: *resource,
+                amount_per_day: goods,
+            })
+            .chain(std::iter::once(Quota {
+                resource: Resource::Passengers,
+                amount_per_day: self.passengers_per_day() as usize,
+            }))
+            .collect()
+    }
+
+    fn passengers_per_day(&self) -> u64 {
+        (self.population / RESIDENTS_PER_PASSENGER) as u64
+    }
+
+    /// Notes down a delivery, which counts towards how well the town's being served today.
+    pub fn receive(&mut self, resource: Resource, amount: usize) {
+        self.received.add(resource, amount);
+    }
+
+    /// How much of what the town wanted today it actually got, from 0 to 1.
+    fn served(&self, demand: &[Quota]) -> f32 {
+        let wanted: usize = demand.iter().map(|quota| quota.amount_per_day).sum();
+        if wanted == 0 {
+            return 1.;
+        }
+        let got: usize = demand
+            .iter()
+            .map(|quota| {
+                let received = self.received.amount(quota.resource);
+                received.min(quota.amount_per_day)
+            })
+            .sum();
+        got as f32 / wanted as f32
+    }
+}
+
+/// Towns are drawn bigger the more people live there.
+fn town_scale(population: u32) -> Vec3 {
+    Vec3::splat(2. + (population as f32).sqrt() / 10.)
+}
+
+pub fn spawn_town<S: Into<String>>(
+    world: &mut hecs::World,
+    name: S,
+    position: Vec3,
+    population: u32,
+) -> hecs::Entity {
+    let town = Town {
+        population,
+        received: Default::default(),
+        day: 0,
+    };
+    let contract = Contract {
+        quotas: town.demand(),
+    };
+    let deposit = Deposit::new(town.passengers_per_day(), PASSENGER_CAP);
+
+    world.spawn((
+        Transform {
+            position,
+            scale: town_scale(population),
+            ..Default::default()
+        },
+        GLTFAsset::new("building.glb"),
+        Business {
+            name: name.into(),
+            contract,
+        },
+        town,
+        Resource::Passengers,
+        deposit,
+        Stockpile::default(),
+        Footprint::around(position, TOWN_RADIUS),
+    ))
+}
+
+/// At the start of every day, towns grow or shrink depending on how well they were served the
+/// day before, and work out what they'll want today. That changes their contracts, so yesterday's
+/// have to be settled up first.
+pub fn town_system(game: &mut Game) {
+    let today = game.calendar.day();
+    for (_, (town, business, deposit, transform)) in
+        game.world
+            .query_mut::<(&mut Town, &mut Business, &mut Deposit, &mut Transform)>()
+    {
+        if town.day == today {
+            continue;
+        }
+        if std::mem::replace(&mut town.day, today) == 0 {
+            // The town's only just been founded.
+            continue;
+        }
+
+        let served = town.served(&business.contract.quotas);
+        let before = town.population;
+        if served >= WELL_SERVED {
+            town.population += (town.population as f32 * GROWTH_RATE).ceil() as u32;
+        } else if served < POORLY_SERVED {
+            let shrink_by = (town.population as f32 * SHRINK_RATE).ceil() as u32;
+            let shrunk = town.population.saturating_sub(shrink_by);
+            town.population = shrunk.max(MIN_POPULATION);
+        }
+        if town.population != before {
+            log::info!(
+                "{} got {:.0}% of what it wanted, and now has {} residents",
+                business.name,
+                served * 100.,
+                town.population
+            );
+        }
+
+        town.received = Default::default();
+        business.contract.quotas = town.demand();
+        deposit.rate = town.passengers_per_day();
+        transform.scale = town_scale(town.population);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        company::PLAYER_COMPANY,
+        economy_system,
+        test_helpers::{next_day, station_with_train},
+        GameEvent,
+    };
+
+    fn quotas(game: &Game, town: hecs::Entity) -> Vec<Quota> {
+        let business = game.world.get::<&Business>(town).unwrap();
+        business.contract.quotas.clone()
+    }
+
+    #[test]
+    fn contracts_are_settled_before_towns_change_what_they_want() {
+        let mut game = Game::default();
+        let town = spawn_town(&mut game.world, "Town", Vec3::ZERO, 500);
+        let (train, station) = station_with_train(&mut game, vec![town], Vec::new());
+        economy_system(&mut game);
+
+        let wanted = quotas(&game, town);
+        for quota in &wanted {
+            game.events.push_back(GameEvent::Delivery {
+                train,
+                station,
+                resource: quota.resource,
+                amount: quota.amount_per_day,
+                consignment: None,
+            });
+        }
+        economy_system(&mut game);
+        game.events.clear();
+
+        next_day(&mut game, economy_system);
+
+        // Every quota was met as it stood yesterday, and the town's grown into bigger ones.
+        assert_eq!(game.contracts.quotas_met(PLAYER_COMPANY), wanted.len());
+        assert_eq!(game.world.get::<&Town>(town).unwrap().population, 525);
+        for (before, after) in wanted.iter().zip(quotas(&game, town)) {
+            assert!(after.amount_per_day > before.amount_per_day, "{after:?}");
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use crate::{
+    spline::{rotation_from_tangent, CubicBezier},
+    ClickState, Game,
+};
+use common::{
+    glam::Vec3,
+    hecs::{self, Entity},
+    log,
+};
+use components::{GLTFAsset, MaterialOverrides, Transform};
+
+/// How far (in metres) track sits above the ground it's laid on.
+pub const TRACK_CLEARANCE: f32 = 0.1;
+/// How long the track model is, in metres. It runs along its Z axis.
+pub const TRACK_MODEL_LENGTH: f32 = 2.;
+const BRIDGE_COLOUR: [f32; 4] = [0.55, 0.35, 0.2, 1.];
+
+/// A piece of track, running along `curve` from its `a` end to its `b` end.
+pub struct TrackSegment {
+    pub a: Option<Entity>,
+    pub b: Option<Entity>,
+    pub curve: CubicBezier,
+}
+
+impl TrackSegment {
+    pub fn new(curve: CubicBezier) -> Self {
+        Self {
+            a: None,
+            b: None,
+            curve,
+        }
+    }
+
+    pub fn length(&self) -> f32 {
+        self.curve.length()
+    }
+
+    /// The end a train heading in `direction` will leave by.
+    pub fn exit_point(&self, direction: Direction) -> Vec3 {
+        match direction {
+            Direction::AToB => self.curve.end(),
+            Direction::BToA => self.curve.start(),
+        }
+    }
+
+    /// Position and heading of something that's travelled `distance` into the segment in
+    /// `direction`.
+    pub fn point_along(&self, direction: Direction, distance: f32) -> (Vec3, Vec3) {
+        match direction {
+            Direction::AToB => (
+                self.curve.position_at_distance(distance),
+                self.curve.tangent_at_distance(distance),
+            ),
+            Direction::BToA => {
+                let distance = self.length() - distance;
+                (
+                    self.curve.position_at_distance(distance),
+                    -self.curve.tangent_at_distance(distance),
+                )
+            }
+        }
+    }
+
+    /// Where the segment's model should sit: halfway along the curve, facing along it, and
+    /// stretched to reach from one end to the other.
+    pub fn transform(&self) -> Transform {
+        let chord = self.curve.start().distance(self.curve.end());
+        Transform::new(
+            self.curve.position(0.5),
+            rotation_from_tangent(self.curve.tangent(0.5)),
+            Vec3::new(1., 1., chord / TRACK_MODEL_LENGTH),
+        )
+    }
+}
+
+/// Which way along a [`TrackSegment`] something is heading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    AToB,
+    BToA,
+}
+
+impl Direction {
+    pub fn reversed(self) -> Self {
+        match self {
+            Direction::AToB => Direction::BToA,
+            Direction::BToA => Direction::AToB,
+        }
+    }
+}
+
+/// Switchable points that sit on the `b` end of a [`TrackSegment`]. A junction can fan out into
+/// any number of branches, and trains leaving through `b` take whichever one is active.
+///
+/// The segment's own `b` is ignored while it has a junction.
+#[derive(Debug, Clone, Default)]
+pub struct Junction {
+    pub branches: Vec<Entity>,
+    pub active_branch: usize,
+}
+
+impl Junction {
+    pub fn active(&self) -> Option<Entity> {
+        self.branches.get(self.active_branch).copied()
+    }
+
+    /// Set the points so trains head down `branch`. Returns `false` if it isn't one of ours.
+    pub fn set_active(&mut self, branch: Entity) -> bool {
+        let Some(index) = self.branches.iter().position(|b| *b == branch) else { return false };
+        self.active_branch = index;
+        true
+    }
+
+    /// Throw the points over to the next branch, wrapping back around to the first.
+    pub fn switch(&mut self) {
+        if self.branches.is_empty() {
+            return;
+        }
+        self.active_branch = (self.active_branch + 1) % self.branches.len();
+    }
+}
+
+/// Marks a [`TrackSegment`] that's carried over water rather than laid on the ground. It's the
+/// only kind of track that can cross a river or lake.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bridge;
+
+pub fn spawn_track_segment(world: &mut hecs::World, curve: CubicBezier) -> Entity {
+    let segment = TrackSegment::new(curve);
+    world.spawn((GLTFAsset::new("tracks.glb"), segment.transform(), segment))
+}
+
+/// Lays a segment of track that's a bridge, coloured so it stands out from track on the ground.
+pub fn spawn_bridge(world: &mut hecs::World, curve: CubicBezier) -> Entity {
+    let segment = spawn_track_segment(world, curve);
+    let colour = MaterialOverrides {
+        base_colour_factor: BRIDGE_COLOUR.into(),
+    };
+    world.insert(segment, (Bridge, colour)).unwrap();
+    segment
+}
+
+/// Where does a train heading along `segment` in `direction` end up, given how the points are
+/// currently set?
+pub fn next_segment(
+    world: &hecs::World,
+    segment: Entity,
+    direction: Direction,
+) -> Option<(Entity, Direction)> {
+    let next = match direction {
+        Direction::AToB => match world.get::<&Junction>(segment) {
+            Ok(junction) => junction.active(),
+            Err(_) => world.get::<&TrackSegment>(segment).ok()?.b,
+        },
+        Direction::BToA => world.get::<&TrackSegment>(segment).ok()?.a,
+    }?;
+    Some((next, entry_direction(world, segment, next)))
+}
+
+/// Everywhere a train heading along `segment` in `direction` could go next, however the points
+/// are set. Trains can't turn around at a junction, so only facing points give more than one.
+pub fn exits(
+    world: &hecs::World,
+    segment: Entity,
+    direction: Direction,
+) -> Vec<(Entity, Direction)> {
+    let Ok(track_segment) = world.get::<&TrackSegment>(segment) else { return Vec::new() };
+    let connected = match direction {
+        Direction::AToB => match world.get::<&Junction>(segment) {
+            Ok(junction) => junction.branches.clone(),
+            Err(_) => track_segment.b.into_iter().collect(),
+        },
+        Direction::BToA => track_segment.a.into_iter().collect(),
+    };
+
+    connected
+        .into_iter()
+        .map(|next| (next, entry_direction(world, segment, next)))
+        .collect()
+}
+
+/// Coming off `from` onto `to`, which way along `to` will we be heading?
+fn entry_direction(world: &hecs::World, from: Entity, to: Entity) -> Direction {
+    if world.get::<&TrackSegment>(to).unwrap().a == Some(from) {
+        Direction::AToB
+    } else {
+        Direction::BToA
+    }
+}
+
+/// Joins the `b` end of `from` to the `a` end of `to`. If `from` is a junction, `to` becomes
+/// another one of its branches.
+pub fn connect_segments(world: &mut hecs::World, from: Entity, to: Entity) {
+    if let Ok(mut junction) = world.get::<&mut Junction>(from) {
+        junction.branches.push(to);
+    } else {
+        world.get::<&mut TrackSegment>(from).unwrap().b = Some(to);
+    }
+    world.get::<&mut TrackSegment>(to).unwrap().a = Some(from);
+}
+
+/// Clicking on a junction throws its points, unless a train has already been given a path over
+/// them.
+pub fn junction_switch_system(game: &mut Game) {
+    let mouse_state = &game.input.mouse_state;
+    if mouse_state.left_click_state != ClickState::JustReleased {
+        return;
+    }
+    let Some(mouse_position) = mouse_state.position else { return };
+
+    let ray = game.camera.create_ray(mouse_position);
+    game.last_ray = Some(ray);
+
+    let Some(entity) = game.physics_context.cast_ray(&ray) else { return };
+    let Ok(mut junction) = game.world.get::<&mut Junction>(entity) else { return };
+    if let Some(block) = game.signalling.block_of(entity) {
+        if game.signalling.is_claimed(block) {
+            log::info!("Junction {entity:?} is locked; there's a train on its way over it");
+            return;
+        }
+    }
+    junction.switch();
+    log::info!(
+        "Switched junction {entity:?} to branch {}",
+        junction.active_branch
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn track_is_drawn_from_end_to_end() {
+        let mut world = hecs::World::new();
+        let short = spawn_track_segment(&mut world, CubicBezier::straight(Vec3::ZERO, Vec3::X));
+        let curve = CubicBezier::straight(Vec3::ZERO, Vec3::new(30., 0., 40.));
+        let bridge = spawn_bridge(&mut world, curve);
+
+        let transform = *world.get::<&Transform>(short).unwrap();
+        assert!(transform.position.distance(Vec3::X * 0.5) < 1e-4);
+        assert_eq!(transform.scale, Vec3::new(1., 1., 0.5));
+
+        // However far it reaches, the model covers the whole span.
+        let transform = *world.get::<&Transform>(bridge).unwrap();
+        assert!(transform.position.distance(Vec3::new(15., 0., 20.)) < 1e-4);
+        assert!((transform.scale.z * TRACK_MODEL_LENGTH - 50.).abs() < 1e-4);
+        let along = transform.rotation * Vec3::Z;
+        assert!(along.distance(Vec3::new(0.6, 0., 0.8)) < 1e-4);
+    }
+}
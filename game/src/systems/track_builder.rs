@@ -0,0 +1,495 @@
+use super::{
+    finances::{charge, BRIDGE_COST_PER_METRE, TRACK_COST_PER_METRE},
+    from_na,
+    track::{
+        connect_segments, spawn_bridge, spawn_track_segment, Junction, TrackSegment,
+        TRACK_CLEARANCE,
+    },
+};
+use crate::{
+    company::{Money, PLAYER_COMPANY},
+    placement::Footprint,
+    spline::CubicBezier,
+    terrain::Terrain,
+    ClickState, Game, Keys,
+};
+use common::{
+    glam::{Vec2, Vec3},
+    hecs::{self, Entity},
+    log,
+    rapier3d::prelude::Ray,
+};
+use components::{GLTFAsset, MaterialOverrides};
+
+/// How close (in metres) the cursor has to be to a loose end of track to pick it up or join it.
+const SNAP_DISTANCE: f32 = 1.;
+const MIN_SEGMENT_LENGTH: f32 = 0.5;
+const MAX_SEGMENT_LENGTH: f32 = 4.;
+/// The furthest a bridge can reach across water, in metres. Bridges have to get from dry land to
+/// dry land in one go.
+const MAX_BRIDGE_SPAN: f32 = 40.;
+/// The sharpest a new segment can bend away from the track it's extending, in radians. The
+/// segment turns through twice this by the time it reaches its far end.
+const MAX_CURVE_ANGLE: f32 = std::f32::consts::PI / 8.;
+/// Rise over run. New track follows the ground, so this rules out laying it up steep hillsides.
+const MAX_GRADIENT: f32 = 0.1;
+/// How much room track needs either side of the middle of it, in metres.
+const TRACK_HALF_WIDTH: f32 = 0.75;
+/// How much of each end of a new segment isn't checked for anything in the way, as it's bound to
+/// be touching whatever it joins on to.
+const JOINT_ALLOWANCE: f32 = 0.5;
+
+const GHOST_COLOUR: [f32; 4] = [0.3, 0.6, 1., 0.5];
+const GHOST_INVALID_COLOUR: [f32; 4] = [1., 0.2, 0.2, 0.5];
+
+/// Construction mode, where the player drags new track out from the loose ends of the network.
+/// Toggled with `C`.
+#[derive(Debug, Default)]
+pub struct TrackBuilder {
+    pub active: bool,
+    toggle_held: bool,
+    dragging_from: Option<TrackEnd>,
+    /// The preview of the segment we'd lay if the player let go now.
+    ghost: Option<Entity>,
+}
+
+impl TrackBuilder {
+    /// Drops whatever we were dragging out, and gets rid of the preview.
+    fn cancel(&mut self, world: &mut hecs::World) {
+        self.dragging_from = None;
+        if let Some(ghost) = self.ghost.take() {
+            let _ = world.despawn(ghost);
+        }
+    }
+}
+
+/// Which end of a [`TrackSegment`] we mean.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum End {
+    A,
+    B,
+}
+
+/// An end of a segment with nothing connected to it.
+#[derive(Debug, Clone, Copy)]
+struct TrackEnd {
+    segment: Entity,
+    end: End,
+    position: Vec3,
+    /// Pointing away from the segment, the way new track laid from here would head.
+    heading: Vec3,
+}
+
+/// A segment the player could lay, and what it would join up with.
+struct Plan {
+    curve: CubicBezier,
+    joins: Option<TrackEnd>,
+    /// Whether it crosses water, and so has to be a bridge.
+    bridge: bool,
+    valid: bool,
+}
+
+pub fn track_builder_system(game: &mut Game) {
+    let toggle_pressed = game.input.is_pressed(Keys::C);
+    let builder = &mut game.track_builder;
+    let world = &mut game.world;
+    if toggle_pressed && !builder.toggle_held {
+        builder.active = !builder.active;
+        let state = if builder.active { "on" } else { "off" };
+        log::info!("Construction mode {state}");
+    }
+    builder.toggle_held = toggle_pressed;
+
+    let mouse_state = &game.input.mouse_state;
+    if !builder.active || mouse_state.right_click_state == ClickState::JustReleased {
+        builder.cancel(world);
+        return;
+    }
+    let Some(mouse_position) = mouse_state.position else { return };
+    let ray = game.camera.create_ray(mouse_position);
+    let ends = free_ends(world);
+
+    let Some(from) = builder.dragging_from else {
+        if mouse_state.left_click_state == ClickState::Down {
+            builder.dragging_from = nearest_end(&ends, &ray, |_| true);
+        }
+        return;
+    };
+
+    // We only need the target's bearing from the end we're dragging, as track laid towards it
+    // follows the ground.
+    let Some(target) = ray_at_height(&ray, from.position.y) else { return };
+    let snap = nearest_end(&ends, &ray, |end| {
+        end.segment != from.segment && end.end != from.end
+    });
+    let plan = plan_segment(&game.terrain, from, target, snap);
+    let preview = TrackSegment::new(plan.curve.clone());
+    let cost_per_metre = if plan.bridge {
+        BRIDGE_COST_PER_METRE
+    } else {
+        TRACK_COST_PER_METRE
+    };
+    let cost = (preview.length() * cost_per_metre as f32).round() as Money;
+    let affordable = game.ledger.balance(PLAYER_COMPANY) >= cost;
+    let clear = is_clear(world, &plan.curve);
+
+    let ghost_colour = if plan.valid && affordable && clear {
+        GHOST_COLOUR
+    } else {
+        GHOST_INVALID_COLOUR
+    };
+    let transform = preview.transform();
+    let colour = MaterialOverrides {
+        base_colour_factor: ghost_colour.into(),
+    };
+    match builder.ghost {
+        Some(ghost) => world.insert(ghost, (transform, colour)).unwrap(),
+        None => {
+            let ghost = world.spawn((GLTFAsset::new("tracks.glb"), transform, colour));
+            builder.ghost = Some(ghost);
+        }
+    }
+
+    match mouse_state.left_click_state {
+        ClickState::Down => {}
+        ClickState::JustReleased if plan.valid && clear => {
+            let day = game.calendar.day();
+            let description = if plan.bridge {
+                "building a bridge"
+            } else {
+                "laying track"
+            };
+            match charge(&mut game.ledger, PLAYER_COMPANY, day, cost, description) {
+                Ok(()) => {
+                    let segment = build(world, from, plan);
+                    game.signalling.layout_changed();
+                    log::info!("Laid new track segment {segment:?}");
+                }
+                Err(e) => log::warn!("Can't lay track: {e}"),
+            }
+            builder.cancel(world);
+        }
+        ClickState::JustReleased if plan.valid => {
+            log::info!("Can't lay track there, as something's in the way");
+            builder.cancel(world);
+        }
+        _ => builder.cancel(world),
+    }
+}
+
+/// Every loose end in the network. A junction's `b` end never counts, as it's spoken for by its
+/// branches.
+fn free_ends(world: &hecs::World) -> Vec<TrackEnd> {
+    let mut ends = Vec::new();
+    for (segment, track_segment) in world.query::<&TrackSegment>().iter() {
+        let curve = &track_segment.curve;
+        if track_segment.a.is_none() {
+            ends.push(TrackEnd {
+                segment,
+                end: End::A,
+                position: curve.start(),
+                heading: -curve.tangent(0.),
+            });
+        }
+        if track_segment.b.is_none() && world.get::<&Junction>(segment).is_err() {
+            ends.push(TrackEnd {
+                segment,
+                end: End::B,
+                position: curve.end(),
+                heading: curve.tangent(1.),
+            });
+        }
+    }
+    ends
+}
+
+/// The closest end to where `ray` passes through its height, if any are close enough.
+fn nearest_end(
+    ends: &[TrackEnd],
+    ray: &Ray,
+    filter: impl Fn(&TrackEnd) -> bool,
+) -> Option<TrackEnd> {
+    ends.iter()
+        .filter(|end| filter(end))
+        .filter_map(|end| {
+            let distance = ray_at_height(ray, end.position.y)?.distance(end.position);
+            (distance <= SNAP_DISTANCE).then_some((distance, *end))
+        })
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, end)| end)
+}
+
+/// Where `ray` crosses the horizontal plane at `height`.
+fn ray_at_height(ray: &Ray, height: f32) -> Option<Vec3> {
+    let origin: Vec3 = from_na(ray.origin);
+    let direction: Vec3 = from_na(ray.dir);
+    if direction.y.abs() < f32::EPSILON {
+        return None;
+    }
+    let toi = (height - origin.y) / direction.y;
+    (toi >= 0.).then(|| origin + direction * toi)
+}
+
+/// Works out the segment to lay from `from` towards `target`. Left to itself the segment is bent
+/// and shortened to fit the constraints, and ends on the ground; when it's joining `snap` it has
+/// to fit as it is. Either way, if it crosses water it's a bridge.
+fn plan_segment(terrain: &Terrain, from: TrackEnd, target: Vec3, snap: Option<TrackEnd>) -> Plan {
+    let heading = flatten(from.heading).normalize_or_zero();
+
+    let Some(snap) = snap else {
+        let run = flatten(target - from.position);
+        let angle = if run == Vec2::ZERO {
+            0.
+        } else {
+            heading
+                .angle_between(run)
+                .clamp(-MAX_CURVE_ANGLE, MAX_CURVE_ANGLE)
+        };
+
+        // Over land we lay a short stretch at a time, but water has to be crossed in one go.
+        let plan = plan_towards(terrain, from, angle, run.length().min(MAX_SEGMENT_LENGTH));
+        if !plan.bridge {
+            return plan;
+        }
+        return plan_towards(terrain, from, angle, run.length().min(MAX_BRIDGE_SPAN));
+    };
+
+    let chord = snap.position - from.position;
+    let run = flatten(chord);
+    let arrival = -flatten(snap.heading);
+    let curve = curve_from(from, snap.position, -snap.heading);
+    let bridge = crosses_water(terrain, &curve);
+    let valid = within_limits(run.length(), bridge)
+        && heading.angle_between(run).abs() <= MAX_CURVE_ANGLE
+        && run.angle_between(arrival).abs() <= MAX_CURVE_ANGLE
+        && chord.y.abs() <= run.length() * MAX_GRADIENT;
+
+    Plan {
+        curve,
+        joins: Some(snap),
+        bridge,
+        valid,
+    }
+}
+
+/// A segment `length` metres long that sets off from `from`, bending through `angle` on the way
+/// and coming down on the ground at the far end.
+fn plan_towards(terrain: &Terrain, from: TrackEnd, angle: f32, length: f32) -> Plan {
+    let heading = flatten(from.heading).normalize_or_zero();
+    let run = Vec2::from_angle(angle).rotate(heading) * length;
+
+    // Bending evenly, we leave at the same angle to the chord as we arrived.
+    let chord = run.normalize_or_zero();
+    let end_heading = chord * 2. * heading.dot(chord) - heading;
+    let end = terrain.surface(from.position + Vec3::new(run.x, 0., run.y));
+    let end = end + Vec3::Y * TRACK_CLEARANCE;
+    let gradient = (end.y - from.position.y) / length.max(f32::EPSILON);
+
+    let curve = curve_from(from, end, Vec3::new(end_heading.x, gradient, end_heading.y));
+    let bridge = crosses_water(terrain, &curve);
+    let valid = within_limits(length, bridge)
+        && heading != Vec2::ZERO
+        && gradient.abs() <= MAX_GRADIENT
+        && !terrain.is_water(end.x, end.z);
+
+    Plan {
+        curve,
+        joins: None,
+        bridge,
+        valid,
+    }
+}
+
+/// The curve from `from` to `end`, arriving heading along `end_heading`.
+fn curve_from(from: TrackEnd, end: Vec3, end_heading: Vec3) -> CubicBezier {
+    // Segments always run from `a` to `b`, so track laid off an `a` end has to run backwards.
+    match from.end {
+        End::B => CubicBezier::from_endpoints(from.position, from.heading, end, end_heading),
+        End::A => CubicBezier::from_endpoints(end, -end_heading, from.position, -from.heading),
+    }
+}
+
+/// Whether any of `curve` runs over water, looking close enough together along it that no river
+/// could slip through.
+fn crosses_water(terrain: &Terrain, curve: &CubicBezier) -> bool {
+    let spacing = terrain.water_sample_spacing();
+    let samples = (curve.length() / spacing).ceil().max(1.) as usize;
+    (0..=samples).any(|sample| {
+        let position = curve.position(sample as f32 / samples as f32);
+        terrain.is_water(position.x, position.z)
+    })
+}
+
+/// Whether `curve` keeps clear of the ground everything on the map takes up, checking every
+/// half a track's width along it. The ends aren't checked, as they're bound to be touching
+/// whatever they join on to.
+fn is_clear(world: &hecs::World, curve: &CubicBezier) -> bool {
+    let length = curve.length() - JOINT_ALLOWANCE * 2.;
+    if length <= 0. {
+        return true;
+    }
+    let samples = (length / TRACK_HALF_WIDTH).ceil() as usize;
+    let points: Vec<Vec2> = (0..=samples)
+        .map(|sample| {
+            let distance = JOINT_ALLOWANCE + length * sample as f32 / samples as f32;
+            flatten(curve.position_at_distance(distance))
+        })
+        .collect();
+
+    // Nothing further than this from the middle of the curve can reach it.
+    let middle = flatten(curve.position_at_distance(curve.length() / 2.));
+    let reach = length / 2. + TRACK_HALF_WIDTH;
+    world.query::<&Footprint>().iter().all(|(_, footprint)| {
+        !footprint.overlaps(middle, reach)
+            || !points
+                .iter()
+                .any(|point| footprint.overlaps(*point, TRACK_HALF_WIDTH))
+    })
+}
+
+fn within_limits(length: f32, bridge: bool) -> bool {
+    let max_length = if bridge {
+        MAX_BRIDGE_SPAN
+    } else {
+        MAX_SEGMENT_LENGTH
+    };
+    (MIN_SEGMENT_LENGTH..=max_length).contains(&length)
+}
+
+fn flatten(v: Vec3) -> Vec2 {
+    Vec2::new(v.x, v.z)
+}
+
+/// Lays the planned segment and connects it up to the track at either end.
+fn build(world: &mut hecs::World, from: TrackEnd, plan: Plan) -> Entity {
+    let segment = if plan.bridge {
+        spawn_bridge(world, plan.curve)
+    } else {
+        spawn_track_segment(world, plan.curve)
+    };
+    match from.end {
+        End::B => {
+            connect_segments(world, from.segment, segment);
+            if let Some(joins) = plan.joins {
+                connect_segments(world, segment, joins.segment);
+            }
+        }
+        End::A => {
+            connect_segments(world, segment, from.segment);
+            if let Some(joins) = plan.joins {
+                connect_segments(world, joins.segment, segment);
+            }
+        }
+    }
+    segment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_generation::spawn_tree;
+
+    #[test]
+    fn track_cant_be_laid_through_things() {
+        let mut world = hecs::World::new();
+        let through = CubicBezier::straight(Vec3::ZERO, Vec3::X * 4.);
+        let beside = CubicBezier::straight(Vec3::Z * 3., Vec3::new(4., 0., 3.));
+        assert!(is_clear(&world, &through));
+
+        spawn_tree(&mut world, Vec3::X * 2.);
+        assert!(!is_clear(&world, &through));
+        assert!(is_clear(&world, &beside));
+    }
+
+    /// A loose end `x` metres along flat ground, `rise` metres above where track would sit.
+    fn loose_end(world: &mut hecs::World, end: End, x: f32, rise: f32, heading: Vec3) -> TrackEnd {
+        TrackEnd {
+            segment: world.spawn(()),
+            end,
+            position: Vec3::new(x, TRACK_CLEARANCE + rise, 0.),
+            heading,
+        }
+    }
+
+    #[test]
+    fn drags_are_laid_as_they_are_if_they_fit() {
+        let mut world = hecs::World::new();
+        let terrain = Terrain::default();
+        let from = loose_end(&mut world, End::B, 0., 0., Vec3::X);
+
+        let plan = plan_segment(&terrain, from, Vec3::new(3., 5., 0.), None);
+        assert!(plan.valid);
+        assert!(!plan.bridge);
+        assert!(plan.joins.is_none());
+        assert_eq!(plan.curve.start(), from.position);
+        let end = Vec3::new(3., TRACK_CLEARANCE, 0.);
+        assert!(plan.curve.end().distance(end) < 1e-4);
+
+        // Off an `a` end, the new segment runs backwards into it.
+        let from = loose_end(&mut world, End::A, 0., 0., -Vec3::X);
+        let plan = plan_segment(&terrain, from, Vec3::new(-3., 0., 0.), None);
+        assert!(plan.valid);
+        assert_eq!(plan.curve.end(), from.position);
+        let start = Vec3::new(-3., TRACK_CLEARANCE, 0.);
+        assert!(plan.curve.start().distance(start) < 1e-4);
+    }
+
+    #[test]
+    fn drags_are_cut_down_to_fit() {
+        let mut world = hecs::World::new();
+        let terrain = Terrain::default();
+        let from = loose_end(&mut world, End::B, 0., 0., Vec3::X);
+
+        // Too long, and it stops short.
+        let plan = plan_segment(&terrain, from, Vec3::new(10., 0., 0.), None);
+        assert!(plan.valid);
+        let end = plan.curve.end();
+        assert!(end.distance(Vec3::new(MAX_SEGMENT_LENGTH, TRACK_CLEARANCE, 0.)) < 1e-4);
+
+        // Too sharp, and it bends as far as it can.
+        let plan = plan_segment(&terrain, from, Vec3::new(0., 0., 3.), None);
+        assert!(plan.valid);
+        let run = flatten(plan.curve.end() - from.position);
+        assert!((run.length() - 3.).abs() < 1e-4);
+        assert!((Vec2::X.angle_between(run) - MAX_CURVE_ANGLE).abs() < 1e-4);
+
+        // Too short, and there's nothing to lay.
+        let plan = plan_segment(&terrain, from, Vec3::new(0.2, 0., 0.), None);
+        assert!(!plan.valid);
+    }
+
+    #[test]
+    fn drags_cant_be_too_steep() {
+        let mut world = hecs::World::new();
+        let terrain = Terrain::default();
+
+        let gentle = loose_end(&mut world, End::B, 0., 0.2, Vec3::X);
+        assert!(plan_segment(&terrain, gentle, Vec3::new(3., 0., 0.), None).valid);
+
+        let steep = loose_end(&mut world, End::B, 0., 1., Vec3::X);
+        assert!(!plan_segment(&terrain, steep, Vec3::new(3., 0., 0.), None).valid);
+    }
+
+    #[test]
+    fn snapped_drags_have_to_fit_as_they_are() {
+        let mut world = hecs::World::new();
+        let terrain = Terrain::default();
+        let from = loose_end(&mut world, End::B, 0., 0., Vec3::X);
+        let target = Vec3::new(3., 0., 0.);
+
+        let facing = loose_end(&mut world, End::A, 3., 0., -Vec3::X);
+        let plan = plan_segment(&terrain, from, target, Some(facing));
+        assert!(plan.valid);
+        assert_eq!(plan.joins.map(|end| end.segment), Some(facing.segment));
+        assert_eq!(plan.curve.end(), facing.position);
+
+        let sideways = loose_end(&mut world, End::A, 3., 0., Vec3::Z);
+        assert!(!plan_segment(&terrain, from, target, Some(sideways)).valid);
+
+        let above = loose_end(&mut world, End::A, 3., 1., -Vec3::X);
+        assert!(!plan_segment(&terrain, from, target, Some(above)).valid);
+
+        let distant = loose_end(&mut world, End::A, 10., 0., -Vec3::X);
+        assert!(!plan_segment(&terrain, from, target, Some(distant)).valid);
+    }
+}
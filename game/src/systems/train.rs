@@ -1,45 +1,560 @@
-use crate::Game;
-use common::hecs::Entity;
-use components::Transform;
+use super::{
+    orders::{follow_orders, Order, Orders},
+    signalling::Signalling,
+    station::{dwell_at, Consignment, Dwell},
+    track::{next_segment, Direction, Junction, TrackSegment},
+};
+use crate::{
+    company::CompanyId,
+    dynamics::{self, Locomotive},
+    routing::find_route,
+    spline::rotation_from_tangent,
+    ClickState, Game,
+};
+use common::{
+    glam::Vec3,
+    hecs::{self, Entity},
+    log,
+};
+use components::{Collider, GLTFAsset, Resource, Stockpile, Transform, Velocity};
+use std::collections::VecDeque;
 
-pub struct TrackSegment {
-    pub a: Option<Entity>,
-    pub b: Option<Entity>,
-}
+/// A number the player can use to tell trains apart, which unlike an `Entity` never gets reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TrainId(pub u32);
 
+/// Lives on the locomotive, and drives the whole consist.
 pub struct Train {
+    pub id: TrainId,
+    pub name: String,
+    pub owner: CompanyId,
+    /// The segment the front of the train is on.
     pub current_segment: Entity,
+    /// Which way the train is heading along `current_segment`.
+    pub direction: Direction,
+    /// How far the front of the train has travelled into `current_segment`.
+    pub distance: f32,
+    /// Segments the front of the train has already passed through, most recent first. We only
+    /// keep as many as it takes to fit the rest of the consist.
+    pub trail: VecDeque<(Entity, Direction)>,
+    /// Every car in the consist from front to back. This starts with the locomotive, but it ends
+    /// up at the back if the train has to reverse.
+    pub cars: Vec<Entity>,
+    /// Length of the whole consist, couplings included.
+    pub length: f32,
+    /// The segment we're headed for. Trains without one stay where they are.
+    pub destination: Option<Entity>,
+    /// Segments still to come on the way to `destination`, after the one we're on.
+    pub route: VecDeque<(Entity, Direction)>,
+    /// Set while we're stopped at a station. Trains don't go anywhere until it's over.
+    pub dwell: Option<Dwell>,
+    /// What the train's been told to do. Trains without orders just go to `destination`.
+    pub orders: Orders,
+    /// Where the goods on board came from, for working out what they're worth.
+    pub consignment: Option<Consignment>,
+}
+
+impl Train {
+    /// Every segment the consist is sitting on, starting with the one the front is on.
+    pub fn segments(&self) -> impl Iterator<Item = (Entity, Direction)> + '_ {
+        std::iter::once((self.current_segment, self.direction)).chain(self.trail.iter().copied())
+    }
+}
+
+/// One vehicle in a consist.
+pub struct Car {
+    pub train: Entity,
+    pub length: f32,
+    /// Mass of the car when empty, in kilograms.
+    pub tare_mass: f32,
+}
+
+/// Every unit of cargo weighs a tonne, whatever it is.
+pub const CARGO_UNIT_MASS: f32 = 1_000.;
+
+/// What a wagon can carry, and what it's carrying.
+#[derive(Debug, Clone, Default)]
+pub struct Cargo {
+    /// The kinds of resource the wagon is fitted out for.
+    pub accepts: Vec<Resource>,
+    /// How many units the wagon can hold, all told.
+    pub capacity: usize,
+    contents: Stockpile,
+}
+
+impl Cargo {
+    pub fn new(accepts: Vec<Resource>, capacity: usize) -> Self {
+        Self {
+            accepts,
+            capacity,
+            contents: Default::default(),
+        }
+    }
+
+    pub fn contents(&self) -> &Stockpile {
+        &self.contents
+    }
+
+    pub fn accepts(&self, resource: Resource) -> bool {
+        self.accepts.contains(&resource)
+    }
+
+    pub fn space(&self) -> usize {
+        self.capacity.saturating_sub(self.contents.total())
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.space() == 0
+    }
+
+    /// Mass of the contents, in kilograms.
+    pub fn mass(&self) -> f32 {
+        self.contents.total() as f32 * CARGO_UNIT_MASS
+    }
+
+    /// Moves up to `amount` of `resource` from `from` into the wagon, as far as there's room
+    /// and the wagon will take it. Returns how much was moved.
+    pub fn load(&mut self, from: &mut Stockpile, resource: Resource, amount: usize) -> usize {
+        if !self.accepts(resource) {
+            return 0;
+        }
+        let moved = from.take(resource, amount.min(self.space()));
+        self.contents.add(resource, moved);
+        moved
+    }
+
+    /// Moves up to `amount` of `resource` out of the wagon into `to`. Returns how much was moved.
+    pub fn unload(&mut self, to: &mut Stockpile, resource: Resource, amount: usize) -> usize {
+        let moved = self.contents.take(resource, amount);
+        to.add(resource, moved);
+        moved
+    }
+
+    /// Empties the wagon into `to`.
+    pub fn unload_all(&mut self, to: &mut Stockpile) {
+        for (resource, amount) in self.contents.take_all() {
+            to.add(resource, amount);
+        }
+    }
+}
+
+/// How far above the track's curve the train model sits.
+const TRAIN_HEIGHT: f32 = 0.3;
+
+const LOCOMOTIVE_LENGTH: f32 = 1.6;
+const WAGON_LENGTH: f32 = 1.2;
+const COUPLING_GAP: f32 = 0.2;
+const LOCOMOTIVE_MASS: f32 = 20_000.;
+const WAGON_MASS: f32 = 5_000.;
+pub const WAGON_CAPACITY: usize = 20;
+
+/// Drivers aim to come to a stand this far short of wherever they need to stop.
+const STOPPING_MARGIN: f32 = 0.1;
+
+/// The furthest ahead a driver looks (and reserves track). A train that can't stop at all, say
+/// on a steep enough downhill, brakes for anything within this distance and hopes for the best.
+const MAX_LOOKAHEAD: f32 = 200.;
+
+/// A train the map comes with, waiting to be put on the track when the game starts.
+#[derive(Debug, Clone)]
+pub struct StartingTrain {
+    pub name: String,
+    /// The segment the train starts out on.
+    pub segment: Entity,
+    pub wagons: Vec<Cargo>,
+    pub orders: Vec<Order>,
 }
 
-const TRAIN_SPEED: f32 = 1.0;
+/// Spawns a locomotive pulling `wagons`, with the whole consist on the track just
+/// past the `a` end of `start_segment`. Use [`crate::Game::spawn_train`] rather than calling this
+/// directly, so IDs are handed out properly.
+pub fn spawn_train(
+    world: &mut hecs::World,
+    id: TrainId,
+    name: String,
+    owner: CompanyId,
+    start_segment: Entity,
+    wagons: Vec<Cargo>,
+) -> Entity {
+    let locomotive = world.spawn((
+        Transform::default(),
+        GLTFAsset::new("train.glb"),
+        Velocity::default(),
+        Locomotive::default(),
+        Collider::default(),
+    ));
+    world
+        .insert_one(
+            locomotive,
+            Car {
+                train: locomotive,
+                length: LOCOMOTIVE_LENGTH,
+                tare_mass: LOCOMOTIVE_MASS,
+            },
+        )
+        .unwrap();
+
+    let mut cars = vec![locomotive];
+    for cargo in wagons {
+        cars.push(world.spawn((
+            Car {
+                train: locomotive,
+                length: WAGON_LENGTH,
+                tare_mass: WAGON_MASS,
+            },
+            cargo,
+            // There's no wagon model yet, so wagons are boxes as long as the wagon. The cube is
+            // two metres along each side.
+            Transform {
+                scale: Vec3::new(0.3, 0.3, WAGON_LENGTH / 2.),
+                ..Default::default()
+            },
+            GLTFAsset::new("cube.glb"),
+            Collider::default(),
+        )));
+    }
+
+    let length = cars
+        .iter()
+        .map(|car| world.get::<&Car>(*car).unwrap().length + COUPLING_GAP)
+        .sum::<f32>()
+        - COUPLING_GAP;
+
+    let mut train = Train {
+        id,
+        name,
+        owner,
+        current_segment: start_segment,
+        direction: Direction::AToB,
+        distance: 0.,
+        trail: Default::default(),
+        cars,
+        length,
+        destination: None,
+        route: Default::default(),
+        dwell: None,
+        orders: Default::default(),
+        consignment: None,
+    };
+
+    // Pull the consist forward until all of it is on the track.
+    advance(world, &mut train, length);
+    place_cars(world, &train);
+    world.insert_one(locomotive, train).unwrap();
+
+    locomotive
+}
+
+/// Removes a train and every car in it from the world, returning the entities that went.
+pub fn despawn_train(world: &mut hecs::World, train: Entity) -> Vec<Entity> {
+    let Ok(cars) = world.get::<&Train>(train).map(|t| t.cars.clone()) else { return Vec::new() };
+    for car in &cars {
+        let _ = world.despawn(*car);
+    }
+    cars
+}
 
 pub fn train_system(game: &mut Game) {
+    let dt = game.time.delta();
     let world = &game.world;
+    let signalling = &mut game.signalling;
+    let layout = signalling.layout();
 
-    let mut train = world.get::<&mut Train>(game.train).unwrap();
-    let mut train_transform = world.get::<&mut Transform>(game.train).unwrap();
-
-    let mut current_segment_transform = *world
-        .get::<&Transform>(train.current_segment)
-        .unwrap()
-        .clone();
-    // We only care about the xz plane
-    current_segment_transform.position.y = train_transform.position.y;
-
-    // Are we close to the segment?
-    if train_transform
-        .position
-        .distance(current_segment_transform.position)
-        .abs()
-        < 0.1
+    for (entity, (train, locomotive, velocity)) in world
+        .query::<(&mut Train, &Locomotive, &mut Velocity)>()
+        .iter()
     {
-        // If yes, find next segment
-        let Some(next_segment) = world.get::<&TrackSegment>(train.current_segment).unwrap().b else { return };
-        train.current_segment = next_segment;
+        let events = &mut game.events;
+        follow_orders(world, &game.calendar, events, layout, entity, train);
+        drive_train(world, signalling, dt, entity, train, locomotive, velocity);
+    }
+}
+
+/// Clicking on any part of a train focuses it.
+pub fn train_focus_system(game: &mut Game) {
+    let mouse_state = &game.input.mouse_state;
+    if mouse_state.left_click_state != ClickState::JustReleased {
+        return;
+    }
+    let Some(mouse_position) = mouse_state.position else { return };
+
+    let ray = game.camera.create_ray(mouse_position);
+    let Some(entity) = game.physics_context.cast_ray(&ray) else { return };
+    let Ok(car) = game.world.get::<&Car>(entity) else { return };
+    game.focused_train = Some(car.train);
+}
+
+fn drive_train(
+    world: &hecs::World,
+    signalling: &mut Signalling,
+    dt: f32,
+    entity: Entity,
+    train: &mut Train,
+    locomotive: &Locomotive,
+    velocity: &mut Velocity,
+) {
+    let speed = velocity.linear.length();
+    plan_route(world, train, speed);
+
+    let mass = consist_mass(world, train);
+    let resistance = dynamics::running_resistance(mass, speed) + gradient_resistance(world, train);
+
+    // Work out whether we need to get on the brakes to stop in time.
+    let lookahead = (dynamics::braking_distance(speed, mass, locomotive.braking_force, resistance)
+        + STOPPING_MARGIN)
+        .min(MAX_LOOKAHEAD);
+    let must_brake = distance_to_stop(world, signalling, entity, train, lookahead)
+        .map(|distance| distance <= lookahead)
+        .unwrap_or(false);
+
+    let force = if must_brake {
+        -locomotive.braking_force
+    } else if speed < locomotive.max_speed {
+        locomotive.tractive_effort(speed)
+    } else {
+        0.
+    };
+
+    // Brakes and friction can stop us, but they can't send us backwards.
+    let mut speed = (speed + (force - resistance) / mass * dt).max(0.);
+    if !advance(world, train, speed * dt) {
+        speed = 0.;
+    }
+
+    if speed == 0. && train.route.is_empty() && train.destination == Some(train.current_segment) {
+        log::info!("{} has arrived", train.name);
+        train.destination = None;
+        train.dwell = dwell_at(world, train.current_segment);
+    }
+
+    let tangent = place_cars(world, train);
+    velocity.linear = tangent * speed;
+}
+
+/// Makes sure a train with somewhere to be knows how it's getting there. If the only way is
+/// back the way we came, and we're stopped, the train reverses.
+fn plan_route(world: &hecs::World, train: &mut Train, speed: f32) {
+    let Some(destination) = train.destination else { return };
+    if !train.route.is_empty() || destination == train.current_segment {
         return;
     }
 
-    // If no, towards segment
-    let train_to_segment = current_segment_transform.position - train_transform.position;
-    train_transform.position += train_to_segment.normalize() * TRAIN_SPEED * game.time.delta();
+    if let Some(route) = find_route(world, (train.current_segment, train.direction), destination) {
+        train.route = route.steps.into_iter().skip(1).collect();
+        return;
+    }
+
+    if speed == 0. {
+        let (steps_back, _) = walk_back(world, train, train.length);
+        let (tail_segment, tail_direction) = train.segments().nth(steps_back).unwrap();
+        if let Some(route) = find_route(
+            world,
+            (tail_segment, tail_direction.reversed()),
+            destination,
+        ) {
+            reverse(world, train);
+            train.route = route.steps.into_iter().skip(1).collect();
+            return;
+        }
+    }
+
+    log::warn!(
+        "{} has no route from {:?} to {destination:?}",
+        train.name,
+        train.current_segment
+    );
+    train.destination = None;
+}
+
+/// Total mass of the consist, including whatever it's carrying.
+pub fn consist_mass(world: &hecs::World, train: &Train) -> f32 {
+    train.cars.iter().map(|car| car_mass(world, *car)).sum()
+}
+
+/// Mass of one car, including whatever it's carrying.
+pub fn car_mass(world: &hecs::World, car: Entity) -> f32 {
+    let tare_mass = world.get::<&Car>(car).unwrap().tare_mass;
+    let payload_mass = world.get::<&Cargo>(car).map_or(0., |cargo| cargo.mass());
+    tare_mass + payload_mass
+}
+
+/// Sums up how hard gravity is pulling on each car, given the slope of the track it's sitting on.
+fn gradient_resistance(world: &hecs::World, train: &Train) -> f32 {
+    let mut resistance = 0.;
+    let mut offset = 0.;
+    for car_entity in &train.cars {
+        let car = world.get::<&Car>(*car_entity).unwrap();
+        let (_, heading) = point_behind(world, train, offset + car.length / 2.);
+        let mass = car_mass(world, *car_entity);
+        resistance += dynamics::gradient_resistance(mass, heading.y);
+        offset += car.length + COUPLING_GAP;
+    }
+    resistance
+}
+
+/// How far the front of the train is from where it needs to stop, if that's within `lookahead`.
+/// Trains stop at the end of their route, at any signal they can't get past, or straight away if
+/// they've nowhere to go or are stopped at a station.
+///
+/// Any block within `lookahead` that we're allowed into gets reserved for us along the way.
+fn distance_to_stop(
+    world: &hecs::World,
+    signalling: &mut Signalling,
+    train_entity: Entity,
+    train: &Train,
+    lookahead: f32,
+) -> Option<f32> {
+    if train.destination.is_none() || train.dwell.is_some() {
+        return Some(0.);
+    }
+
+    let mut distance = segment_length(world, train.current_segment) - train.distance;
+    let mut block = signalling.block_of(train.current_segment);
+    let mut upcoming = train.route.iter();
+    while distance <= lookahead {
+        let Some((segment, _)) = upcoming.next() else { return Some(distance) };
+
+        let next_block = signalling.block_of(*segment);
+        if let Some(next_block) = next_block.filter(|b| Some(*b) != block) {
+            if !signalling.reserve(train_entity, next_block) {
+                return Some(distance);
+            }
+        }
+
+        block = next_block;
+        distance += segment_length(world, *segment);
+    }
+
+    None
+}
+
+/// Moves the front of the train `distance` further along the track, carrying it over onto the
+/// segments ahead. Returns `false` if we ran out of track.
+fn advance(world: &hecs::World, train: &mut Train, distance: f32) -> bool {
+    let mut moving = true;
+    train.distance += distance;
+
+    loop {
+        let length = segment_length(world, train.current_segment);
+        if train.distance < length {
+            break;
+        }
+
+        let Some(next) = next_step(world, train) else {
+            train.distance = length;
+            moving = false;
+            break;
+        };
+        train.distance -= length;
+        train
+            .trail
+            .push_front((train.current_segment, train.direction));
+        (train.current_segment, train.direction) = next;
+    }
+
+    // Forget about any segments the back of the train has left behind.
+    let mut covered = train.distance;
+    let mut keep = 0;
+    for (segment, _) in &train.trail {
+        if covered >= train.length {
+            break;
+        }
+        covered += segment_length(world, *segment);
+        keep += 1;
+    }
+    train.trail.truncate(keep);
+
+    moving
+}
+
+/// Where the front of the train goes once it reaches the end of the segment it's on. Trains on
+/// a route throw any points in their way; anyone else goes wherever the points take them.
+fn next_step(world: &hecs::World, train: &mut Train) -> Option<(Entity, Direction)> {
+    if train.destination.is_none() {
+        return next_segment(world, train.current_segment, train.direction);
+    }
+
+    let next = train.route.pop_front()?;
+    if train.direction == Direction::AToB {
+        if let Ok(mut junction) = world.get::<&mut Junction>(train.current_segment) {
+            junction.set_active(next.0);
+        }
+    }
+    Some(next)
+}
+
+/// Swaps the front and back of the train over, so it can head back the way it came.
+fn reverse(world: &hecs::World, train: &mut Train) {
+    let (steps_back, distance) = walk_back(world, train, train.length);
+    let segments: Vec<_> = train.segments().collect();
+    let (tail_segment, tail_direction) = segments[steps_back];
+
+    train.trail = segments[..steps_back]
+        .iter()
+        .rev()
+        .map(|(segment, direction)| (*segment, direction.reversed()))
+        .collect();
+    train.current_segment = tail_segment;
+    train.direction = tail_direction.reversed();
+    train.distance = segment_length(world, tail_segment) - distance;
+    train.cars.reverse();
+    train.route.clear();
+}
+
+/// Follows the path the train actually took back `offset` from the front. Returns how many
+/// segments back that is (zero being the current segment), and how far into that segment.
+fn walk_back(world: &hecs::World, train: &Train, offset: f32) -> (usize, f32) {
+    let mut remaining = offset - train.distance;
+    if remaining <= 0. {
+        return (0, -remaining);
+    }
+
+    for (index, (segment, _)) in train.trail.iter().enumerate() {
+        let length = segment_length(world, *segment);
+        if remaining <= length {
+            return (index + 1, length - remaining);
+        }
+        remaining -= length;
+    }
+
+    // We've run out of track behind us, so bunch up at the start of it.
+    (train.trail.len(), 0.)
+}
+
+/// Position and heading of the point on the track `offset` behind the front of the train.
+fn point_behind(world: &hecs::World, train: &Train, offset: f32) -> (Vec3, Vec3) {
+    let (steps_back, distance) = walk_back(world, train, offset);
+    let (segment, direction) = match steps_back {
+        0 => (train.current_segment, train.direction),
+        n => train.trail[n - 1],
+    };
+    world
+        .get::<&TrackSegment>(segment)
+        .unwrap()
+        .point_along(direction, distance)
+}
+
+/// Puts each car where it belongs on the track behind the front of the train, returning the
+/// heading of the front of the train.
+fn place_cars(world: &hecs::World, train: &Train) -> Vec3 {
+    let mut heading = Vec3::ZERO;
+    let mut offset = 0.;
+    for car_entity in &train.cars {
+        let car = world.get::<&Car>(*car_entity).unwrap();
+        let (position, tangent) = point_behind(world, train, offset + car.length / 2.);
+        if offset == 0. {
+            heading = tangent;
+        }
+
+        let mut transform = world.get::<&mut Transform>(*car_entity).unwrap();
+        transform.position = position + Vec3::Y * TRAIN_HEIGHT;
+        transform.rotation = rotation_from_tangent(tangent);
+
+        offset += car.length + COUPLING_GAP;
+    }
+    heading
+}
+
+fn segment_length(world: &hecs::World, segment: Entity) -> f32 {
+    world.get::<&TrackSegment>(segment).unwrap().length()
 }
@@ -1,10 +1,17 @@
 use components::{Transform, Velocity};
 
+use super::train::Car;
 use crate::Game;
 
 pub fn update_position_system(game: &mut Game) {
     let dt = game.time.delta();
-    for (_, (transform, velocity)) in game.world.query::<(&mut Transform, &Velocity)>().iter() {
+    // Trains are kept on the rails by `train_system` instead.
+    for (_, (transform, velocity)) in game
+        .world
+        .query::<(&mut Transform, &Velocity)>()
+        .without::<&Car>()
+        .iter()
+    {
         let displacement = velocity.linear * dt;
         transform.position += displacement;
     }
@@ -0,0 +1,356 @@
+// The ground everything sits on: a heightmap built from seeded noise, which we turn into a mesh
+// for the renderer and ask about whenever we need to know how high the ground is somewhere. Rivers
+// are cut into it afterwards, and anywhere below the water line is under water.
+
+use common::{
+    glam::{Vec2, Vec3, Vec4},
+    rand::{rngs::StdRng, Rng, SeedableRng},
+};
+use components::{GLTFModel, Material, Primitive, Vertex};
+use std::sync::Arc;
+
+/// Grid squares along each side of the map.
+const RESOLUTION: usize = 128;
+/// The highest the hills can get, in metres.
+const MAX_HEIGHT: f32 = 20.;
+/// How far apart (in metres) the biggest hills are.
+const FEATURE_SIZE: f32 = 300.;
+/// Each layer of noise adds detail half the size and half the height of the one before.
+const OCTAVES: u32 = 4;
+const GRASS_COLOUR: Vec4 = Vec4::new(0.35, 0.55, 0.25, 1.);
+const WATER_COLOUR: Vec4 = Vec4::new(0.2, 0.45, 0.75, 1.);
+
+/// Any ground lower than this, in metres, is under water. The lowest of the hollows between the
+/// hills fill up and become lakes.
+const WATER_LEVEL: f32 = MAX_HEIGHT * 0.3;
+const MIN_RIVERS: usize = 1;
+const MAX_RIVERS: usize = 3;
+/// How wide rivers are, in metres, and how far below the water line their beds lie.
+const RIVER_WIDTH: f32 = 20.;
+const RIVER_DEPTH: f32 = 2.;
+/// How far (in radians) a river can turn each step, and how far it can wander off course.
+const RIVER_MEANDER: f32 = 0.2;
+const MAX_RIVER_BEARING: f32 = std::f32::consts::FRAC_PI_3;
+/// Everything this close to the middle of the map is kept high and dry, so there's somewhere to
+/// lay the first stretch of track.
+const DRY_RADIUS: f32 = 50.;
+
+#[derive(Debug, Clone)]
+pub struct Terrain {
+    /// Length of each side, in metres. The map is centred on the origin.
+    size: f32,
+    /// Squares along each side.
+    resolution: usize,
+    /// Height at each corner of the grid, a row at a time from the -x, -z corner.
+    heights: Vec<f32>,
+    water_level: f32,
+}
+
+impl Default for Terrain {
+    /// Completely flat, and bone dry.
+    fn default() -> Self {
+        Self {
+            size: 1.,
+            resolution: 1,
+            heights: vec![0.; 4],
+            water_level: f32::NEG_INFINITY,
+        }
+    }
+}
+
+impl Terrain {
+    /// Builds a `size` metre square of rolling hills, with lakes and rivers between them. The same
+    /// seed always gives the same hills, and the same water.
+    pub fn generate(seed: u64, size: f32) -> Self {
+        let cell_size = size / RESOLUTION as f32;
+        let corners = RESOLUTION + 1;
+        let mut heights = Vec::with_capacity(corners * corners);
+        for row in 0..corners {
+            for column in 0..corners {
+                let x = column as f32 * cell_size;
+                let z = row as f32 * cell_size;
+                heights.push(fractal_noise(seed, x / FEATURE_SIZE, z / FEATURE_SIZE) * MAX_HEIGHT);
+            }
+        }
+
+        let mut terrain = Self {
+            size,
+            resolution: RESOLUTION,
+            heights,
+            water_level: WATER_LEVEL,
+        };
+        let mut rng = StdRng::seed_from_u64(seed);
+        for _ in 0..rng.gen_range(MIN_RIVERS..=MAX_RIVERS) {
+            terrain.carve_river(&mut rng);
+        }
+        let dry = WATER_LEVEL + 1.;
+        terrain.reshape(Vec2::ZERO, DRY_RADIUS, |height| height.max(dry));
+        terrain
+    }
+
+    /// A `size` metre square of flat, dry ground, for maps that bring their own scenery.
+    pub fn flat(size: f32) -> Self {
+        Self {
+            size,
+            ..Default::default()
+        }
+    }
+
+    /// How high the ground is at `x`, `z`. Anywhere off the map is as high as the nearest edge.
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        let (column, row) = self.to_grid(x, z);
+        let column = column.clamp(0., self.resolution as f32);
+        let row = row.clamp(0., self.resolution as f32);
+
+        let left = (column.floor() as usize).min(self.resolution - 1);
+        let top = (row.floor() as usize).min(self.resolution - 1);
+        let across = column - left as f32;
+        let down = row - top as f32;
+
+        let near = lerp(self.corner(left, top), self.corner(left + 1, top), across);
+        let far = lerp(
+            self.corner(left, top + 1),
+            self.corner(left + 1, top + 1),
+            across,
+        );
+        lerp(near, far, down)
+    }
+
+    /// The point on the ground directly above (or below) `position`.
+    pub fn surface(&self, position: Vec3) -> Vec3 {
+        let height = self.height_at(position.x, position.z);
+        Vec3::new(position.x, height, position.z)
+    }
+
+    /// Whether `x`, `z` is in a river or lake.
+    pub fn is_water(&self, x: f32, z: f32) -> bool {
+        self.height_at(x, z) < self.water_level
+    }
+
+    /// How far apart to look for water along a line, so as not to step over any. That's a small
+    /// part of a river's width, but lakes can narrow to a point anywhere, so it's also a small
+    /// part of a grid square.
+    pub fn water_sample_spacing(&self) -> f32 {
+        (RIVER_WIDTH / 20.).min(self.cell_size() / 8.)
+    }
+
+    /// Which way is up, as far as the ground at `x`, `z` is concerned.
+    pub fn normal_at(&self, x: f32, z: f32) -> Vec3 {
+        let step = self.cell_size();
+        let dx = self.height_at(x + step, z) - self.height_at(x - step, z);
+        let dz = self.height_at(x, z + step) - self.height_at(x, z - step);
+        Vec3::new(-dx, 2. * step, -dz).normalize()
+    }
+
+    /// A mesh of the whole terrain, in world space.
+    pub fn mesh(&self) -> GLTFModel {
+        let corners = self.resolution + 1;
+        let cell_size = self.cell_size();
+        let half = self.size / 2.;
+
+        let mut vertices = Vec::with_capacity(corners * corners);
+        for row in 0..corners {
+            for column in 0..corners {
+                let x = column as f32 * cell_size - half;
+                let z = row as f32 * cell_size - half;
+                let position = Vec3::new(x, self.corner(column, row), z);
+                let uv = Vec2::new(column as f32, row as f32) / self.resolution as f32;
+                vertices.push(Vertex::new(
+                    position.extend(1.),
+                    self.normal_at(x, z).extend(1.),
+                    uv,
+                ));
+            }
+        }
+
+        let mut indices = Vec::with_capacity(self.resolution * self.resolution * 6);
+        for row in 0..self.resolution {
+            for column in 0..self.resolution {
+                let top_left = (row * corners + column) as u32;
+                let top_right = top_left + 1;
+                let bottom_left = top_left + corners as u32;
+                let bottom_right = bottom_left + 1;
+                indices.extend_from_slice(&[
+                    top_left,
+                    bottom_left,
+                    top_right,
+                    top_right,
+                    bottom_left,
+                    bottom_right,
+                ]);
+            }
+        }
+
+        model(vertices, indices, GRASS_COLOUR)
+    }
+
+    /// A sheet of water at the water line, covering the whole map. The ground pokes up through it
+    /// everywhere that's dry.
+    pub fn water_mesh(&self) -> GLTFModel {
+        let half = self.size / 2.;
+        let vertices = [[-half, -half], [half, -half], [-half, half], [half, half]]
+            .into_iter()
+            .map(|[x, z]| {
+                let uv = Vec2::new(x, z) / self.size + 0.5;
+                Vertex::new(
+                    Vec4::new(x, self.water_level, z, 1.),
+                    Vec4::new(0., 1., 0., 1.),
+                    uv,
+                )
+            })
+            .collect();
+        model(vertices, vec![0, 2, 1, 1, 2, 3], WATER_COLOUR)
+    }
+
+    /// Cuts a river right across the map, from one edge to the opposite one, wandering as it goes.
+    fn carve_river(&mut self, rng: &mut StdRng) {
+        let half = self.size / 2.;
+        let step = self.cell_size() / 2.;
+        let bed = self.water_level - RIVER_DEPTH;
+        // Rivers run either east to west or north to south.
+        let north_to_south = rng.gen_bool(0.5);
+        let mut position = Vec2::new(-half, rng.gen_range(-half..half));
+        let mut bearing: f32 = 0.;
+        while position.x <= half {
+            let centre = if north_to_south {
+                Vec2::new(position.y, position.x)
+            } else {
+                position
+            };
+            self.reshape(centre, RIVER_WIDTH / 2., |height| height.min(bed));
+
+            bearing += rng.gen_range(-RIVER_MEANDER..RIVER_MEANDER);
+            bearing = bearing.clamp(-MAX_RIVER_BEARING, MAX_RIVER_BEARING);
+            position += Vec2::from_angle(bearing) * step;
+        }
+    }
+
+    /// Changes the height of every corner of the grid within `radius` metres of `centre`.
+    fn reshape(&mut self, centre: Vec2, radius: f32, change: impl Fn(f32) -> f32) {
+        let (min_column, min_row) = self.to_grid(centre.x - radius, centre.y - radius);
+        let (max_column, max_row) = self.to_grid(centre.x + radius, centre.y + radius);
+        let last = self.resolution as f32;
+        let cell_size = self.cell_size();
+        let half = self.size / 2.;
+        let rows = min_row.ceil().max(0.) as usize..=max_row.floor().min(last) as usize;
+        let columns = min_column.ceil().max(0.) as usize..=max_column.floor().min(last) as usize;
+        for row in rows {
+            for column in columns.clone() {
+                let corner = Vec2::new(column as f32, row as f32) * cell_size - half;
+                if corner.distance(centre) <= radius {
+                    let index = row * (self.resolution + 1) + column;
+                    self.heights[index] = change(self.heights[index]);
+                }
+            }
+        }
+    }
+
+    fn cell_size(&self) -> f32 {
+        self.size / self.resolution as f32
+    }
+
+    fn to_grid(&self, x: f32, z: f32) -> (f32, f32) {
+        let half = self.size / 2.;
+        ((x + half) / self.cell_size(), (z + half) / self.cell_size())
+    }
+
+    fn corner(&self, column: usize, row: usize) -> f32 {
+        self.heights[row * (self.resolution + 1) + column]
+    }
+}
+
+fn model(vertices: Vec<Vertex>, indices: Vec<u32>, colour: Vec4) -> GLTFModel {
+    GLTFModel {
+        primitives: Arc::new(vec![Primitive {
+            vertices,
+            indices,
+            material: Material {
+                base_colour_factor: colour,
+                ..Default::default()
+            },
+        }]),
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Several layers of value noise added together, coming out somewhere between 0 and 1.
+fn fractal_noise(seed: u64, x: f32, z: f32) -> f32 {
+    let mut total = 0.;
+    let mut amplitude = 1.;
+    let mut frequency = 1.;
+    let mut max = 0.;
+    for octave in 0..OCTAVES {
+        let octave_seed = seed.wrapping_add(octave as u64);
+        total += value_noise(octave_seed, x * frequency, z * frequency) * amplitude;
+        max += amplitude;
+        amplitude /= 2.;
+        frequency *= 2.;
+    }
+    total / max
+}
+
+/// Smoothly blends between random heights at each whole number coordinate.
+fn value_noise(seed: u64, x: f32, z: f32) -> f32 {
+    let (left, top) = (x.floor(), z.floor());
+    let across = smoothstep(x - left);
+    let down = smoothstep(z - top);
+    let (left, top) = (left as i64, top as i64);
+
+    let near = lerp(
+        lattice(seed, left, top),
+        lattice(seed, left + 1, top),
+        across,
+    );
+    let far = lerp(
+        lattice(seed, left, top + 1),
+        lattice(seed, left + 1, top + 1),
+        across,
+    );
+    lerp(near, far, down)
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3. - 2. * t)
+}
+
+/// A random number between 0 and 1 for each point on the lattice. SplitMix64, more or less.
+fn lattice(seed: u64, x: i64, z: i64) -> f32 {
+    let mut hash = seed
+        ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (z as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    hash ^= hash >> 31;
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heights_match_the_mesh() {
+        let terrain = Terrain::generate(1234, 1000.);
+        let mesh = terrain.mesh();
+        for vertex in mesh.primitives[0].vertices.iter().step_by(97) {
+            let position = vertex.position.truncate();
+            let height = terrain.height_at(position.x, position.z);
+            assert!((height - position.y).abs() < 0.001);
+            assert!(height <= MAX_HEIGHT);
+        }
+        assert_eq!(terrain.height_at(-5000., 0.), terrain.height_at(-500., 0.));
+    }
+
+    #[test]
+    fn there_is_water_but_not_in_the_middle() {
+        for seed in 0..10 {
+            let terrain = Terrain::generate(seed, 1000.);
+            assert!(terrain.heights.iter().any(|height| *height < WATER_LEVEL));
+            assert!(!terrain.is_water(0., 0.));
+            assert!(!terrain.is_water(DRY_RADIUS - 1., 0.));
+        }
+    }
+}
@@ -0,0 +1,42 @@
+// Setting up the same few things over and over gets tedious, so the tests share these.
+
+use crate::{
+    company::PLAYER_COMPANY,
+    spline::CubicBezier,
+    systems::{station::Station, track::spawn_track_segment, train::Cargo},
+    Game,
+};
+use common::{glam::Vec3, hecs::Entity};
+use components::Transform;
+
+/// Runs the calendar on to the start of the next day, and lets `system` catch up with it.
+pub fn next_day(game: &mut Game, system: fn(&mut Game)) {
+    for _ in 0..game.calendar.ticks_per_day() {
+        game.calendar.tick();
+    }
+    system(game);
+}
+
+/// A station serving `catchment`, at the far end of a 10 metre stretch of track, with one of the
+/// player's trains stood at the near end pulling `wagons`. Returns the train and the station.
+pub fn station_with_train(
+    game: &mut Game,
+    catchment: Vec<Entity>,
+    wagons: Vec<Cargo>,
+) -> (Entity, Entity) {
+    let curve = CubicBezier::straight(Vec3::ZERO, Vec3::X * 10.);
+    let segment = spawn_track_segment(&mut game.world, curve);
+    let station = game.world.spawn((
+        Station {
+            name: "Station".into(),
+            segment,
+            dwell_time: 0.,
+            catchment_radius: 0.,
+            catchment,
+            supply: Default::default(),
+        },
+        Transform::default(),
+    ));
+    let id = game.spawn_train("Train", PLAYER_COMPANY, segment, wagons);
+    (game.train_entity(id).unwrap(), station)
+}
@@ -1,6 +1,6 @@
 use std::time::Instant;
 
-const UPDATE_RATE: f32 = 1.0 / 60.0;
+pub const UPDATE_RATE: f32 = 1.0 / 60.0;
 const MAX_ACCUMULATOR_MS: f32 = 50.0;
 
 /// A timestep implementation that's actually good.
@@ -8,7 +8,7 @@ pub const BOLT: &str = "\u{f0e7}";
 pub const FORGE: &str = "\u{f06d}";
 pub const FACTORY: &str = "\u{f275}";
 pub const HOUSE: &str = "\u{f015}";
-pub const HAMMER: &str = "\u{f6e3}";
+pub const SUN: &str = "\u{f185}";
 pub const MOON: &str = "\u{f186}";
 
 pub fn icon_text(font_size: f32, icon_codepoint: &'static str) -> Response<TextWidget> {
@@ -16,11 +16,13 @@ use common::{
         widgets::{List, Pad},
         Color, CrossAxisAlignment, MainAxisAlignment, MainAxisSize,
     },
-    GUICommand, PlaceOfWorkInfo, VikingInfo,
+    GUICommand, PlaceOfWorkInfo, SelectedItemInfo, TrainInfo, VikingInfo,
 };
 use icon::icon_text;
 
 pub const CONTAINER_BACKGROUND: Color = Color::rgba(0, 0, 0, 150);
+/// How much the player borrows or pays back with each click.
+const LOAN_STEP: i64 = 10_000;
 
 pub struct GUI {
     pub yak: yakui::Yakui,
@@ -65,6 +67,27 @@ impl GUI {
 pub fn draw_gui(gui: &mut GUI) {
     let gui_state = &mut gui.state;
     gui.yak.start();
+    if gui_state.game_over {
+        game_over(&gui_state.outcome, &mut gui_state.command_queue);
+    } else {
+        column(|| {
+            clock(gui_state);
+            company(gui_state);
+            notices(&gui_state.notices);
+            if gui_state.selected_item.is_some() {
+                let mut row = List::row();
+                row.main_axis_size = MainAxisSize::Max;
+                row.main_axis_alignment = MainAxisAlignment::End;
+                row.show(|| {
+                    selected_item(
+                        &gui_state.selected_item,
+                        gui_state.idle_workers,
+                        &mut gui_state.command_queue,
+                    )
+                });
+            }
+        });
+    }
     gui.yak.finish();
 }
 
@@ -74,6 +97,8 @@ fn clock(gui_state: &mut GUIState) {
     row.main_axis_alignment = MainAxisAlignment::End;
     row.cross_axis_alignment = CrossAxisAlignment::End;
     let clock_description = gui_state.clock_description.clone();
+    let is_daytime = gui_state.is_daytime;
+    let seed = gui_state.seed;
 
     row.show(|| {
         let container = ColoredBox::container(CONTAINER_BACKGROUND);
@@ -90,14 +115,87 @@ fn clock(gui_state: &mut GUIState) {
                     row.cross_axis_alignment = CrossAxisAlignment::Center;
                     row.item_spacing = 5.;
                     row.show(|| {
-                        let icon_glyph = if clock_description == "Work Time" {
-                            icon::HAMMER
-                        } else {
-                            icon::MOON
-                        };
+                        let icon_glyph = if is_daytime { icon::SUN } else { icon::MOON };
                         icon_text(16., icon_glyph);
                         text(16., clock_description);
                     });
+
+                    text(12., format!("Seed {seed}"));
+                });
+            });
+        });
+    });
+}
+
+fn company(gui_state: &mut GUIState) {
+    let mut row = List::row();
+    row.main_axis_size = MainAxisSize::Max;
+    row.main_axis_alignment = MainAxisAlignment::End;
+    let GUIState {
+        cash,
+        loan,
+        command_queue,
+        ..
+    } = gui_state;
+
+    row.show(|| {
+        let container = ColoredBox::container(CONTAINER_BACKGROUND);
+        container.show_children(|| {
+            pad(Pad::all(10.), || {
+                let mut col = widgets::List::column();
+                col.main_axis_size = MainAxisSize::Min;
+                col.cross_axis_alignment = CrossAxisAlignment::End;
+                col.item_spacing = 5.;
+                col.show(|| {
+                    text(20., format!("Cash: {cash}"));
+                    text(16., format!("Loan: {loan}"));
+
+                    let mut row = List::row();
+                    row.item_spacing = 5.;
+                    row.show(|| {
+                        if button(format!("Borrow {LOAN_STEP}")).clicked {
+                            command_queue.push_back(GUICommand::Borrow(LOAN_STEP));
+                        }
+                        if *loan > 0 && button(format!("Repay {LOAN_STEP}")).clicked {
+                            command_queue.push_back(GUICommand::RepayLoan(LOAN_STEP));
+                        }
+                    });
+
+                    let mut row = List::row();
+                    row.item_spacing = 5.;
+                    row.show(|| {
+                        if button("Build station").clicked {
+                            command_queue.push_back(GUICommand::BuildStation);
+                        }
+                        if button("Buy train").clicked {
+                            command_queue.push_back(GUICommand::BuyTrain);
+                        }
+                    });
+                });
+            });
+        });
+    });
+}
+
+fn notices(notices: &[String]) {
+    if notices.is_empty() {
+        return;
+    }
+    let mut row = List::row();
+    row.main_axis_size = MainAxisSize::Max;
+    row.main_axis_alignment = MainAxisAlignment::End;
+    row.show(|| {
+        let container = ColoredBox::container(CONTAINER_BACKGROUND);
+        container.show_children(|| {
+            pad(Pad::all(10.), || {
+                let mut col = widgets::List::column();
+                col.main_axis_size = MainAxisSize::Min;
+                col.cross_axis_alignment = CrossAxisAlignment::End;
+                col.item_spacing = 5.;
+                col.show(|| {
+                    for notice in notices {
+                        text(16., notice.clone());
+                    }
                 });
             });
         });
@@ -110,6 +208,7 @@ fn inspectors(gui_state: &mut GUIState) {
         idle_workers,
         command_queue,
         total_deaths,
+        selected_item: selected,
         ..
     } = gui_state;
     row(|| {
@@ -126,23 +225,32 @@ fn inspectors(gui_state: &mut GUIState) {
         });
         expanded(|| {});
 
-        if let Some((entity, selected_item)) = &gui_state.selected_item {
-            let mut container = widgets::ColoredBox::container(CONTAINER_BACKGROUND);
-            container.min_size.x = 200.;
-            container.show_children(|| {
-                pad(Pad::all(10.), || match selected_item {
-                    common::SelectedItemInfo::Viking(h) => viking(*entity, h, command_queue),
-                    common::SelectedItemInfo::PlaceOfWork(p) => {
-                        place_of_work(*entity, p, *idle_workers, command_queue)
-                    }
-                    common::SelectedItemInfo::Storage(s) => storage(s),
-                });
-            });
-        }
+        selected_item(selected, *idle_workers, command_queue);
     });
 }
 
-fn game_over(paperclip_count: usize, deaths: usize, commands: &mut VecDeque<GUICommand>) {
+fn selected_item(
+    selected: &Option<(hecs::Entity, SelectedItemInfo)>,
+    idle_workers: usize,
+    command_queue: &mut VecDeque<GUICommand>,
+) {
+    if let Some((entity, selected_item)) = selected {
+        let mut container = widgets::ColoredBox::container(CONTAINER_BACKGROUND);
+        container.min_size.x = 200.;
+        container.show_children(|| {
+            pad(Pad::all(10.), || match selected_item {
+                common::SelectedItemInfo::Viking(h) => viking(*entity, h, command_queue),
+                common::SelectedItemInfo::PlaceOfWork(p) => {
+                    place_of_work(*entity, p, idle_workers, command_queue)
+                }
+                common::SelectedItemInfo::Storage(s) => storage(s),
+                common::SelectedItemInfo::Train(t) => train(t),
+            });
+        });
+    }
+}
+
+fn game_over(outcome: &str, commands: &mut VecDeque<GUICommand>) {
     let mut the_box = List::column();
     the_box.main_axis_alignment = MainAxisAlignment::Center;
     the_box.cross_axis_alignment = CrossAxisAlignment::Center;
@@ -157,9 +265,7 @@ fn game_over(paperclip_count: usize, deaths: usize, commands: &mut VecDeque<GUIC
                 column.cross_axis_alignment = CrossAxisAlignment::Center;
                 column.show(|| {
                     text(100., "GAME OVER");
-                    text(50., format!("You made {paperclip_count} paperclips."));
-                    text(50., format!("You were responsible for {deaths} deaths."));
-                    text(50., format!("You maintained AI safety."));
+                    text(50., outcome.to_string());
                     let res = button("Try again");
                     if res.clicked {
                         commands.push_back(GUICommand::Restart);
@@ -178,6 +284,30 @@ fn storage(s: &common::StorageInfo) {
     });
 }
 
+fn train(t: &TrainInfo) {
+    let TrainInfo {
+        id,
+        name,
+        owner,
+        speed,
+        destination,
+        order,
+        cargo,
+        mass,
+    } = t;
+    column(|| {
+        text(30., "Train");
+        text(20., format!("Name: {name}"));
+        text(20., format!("Number: {id}"));
+        text(20., format!("Owner: {owner}"));
+        text(20., format!("Speed: {speed:.1} m/s"));
+        text(20., format!("Destination: {destination}"));
+        text(20., format!("Orders: {order}"));
+        text(20., format!("Cargo: {cargo}"));
+        text(20., format!("Mass: {:.1} t", mass / 1000.));
+    });
+}
+
 fn viking(entity: hecs::Entity, h: &VikingInfo, commands: &mut VecDeque<GUICommand>) {
     let VikingInfo {
         name,
@@ -5,7 +5,7 @@ use vulkan_renderer::LazyVulkan;
 use metal_renderer::MetalRenderer;
 
 use common::{
-    log,
+    anyhow, log,
     winit::{
         self,
         event::{Event, WindowEvent},
@@ -18,13 +18,13 @@ use common::{
 const INITIAL_SCREEN_WIDTH: u32 = 1000;
 const INITIAL_SCREEN_HEIGHT: u32 = 1000;
 
-pub fn init<R: Renderer>() -> (
+pub fn init<R: Renderer>() -> anyhow::Result<(
     R,
     EventLoop<()>,
     gui::GUI,
     game::Game,
     yakui_winit::YakuiWinit,
-) {
+)> {
     env_logger::init();
     log::debug!("Debug logging enabled");
     let event_loop = winit::event_loop::EventLoop::new();
@@ -44,14 +44,14 @@ pub fn init<R: Renderer>() -> (
         .with_fullscreen(Some(winit::window::Fullscreen::Borderless(target_monitor)))
         .build(&event_loop)
         .unwrap();
-    let mut game = game::init();
+    let mut game = game::init()?;
     game.resized(window.inner_size());
     let yak_winit = yakui_winit::YakuiWinit::new(&window);
 
     let renderer = R::init(window);
     let gui = gui::GUI::new(INITIAL_SCREEN_WIDTH, INITIAL_SCREEN_HEIGHT);
 
-    (renderer, event_loop, gui, game, yak_winit)
+    Ok((renderer, event_loop, gui, game, yak_winit))
 }
 
 #[cfg(target_os = "macos")]
@@ -60,9 +60,9 @@ type RendererImpl = MetalRenderer;
 #[cfg(any(target_os = "windows", target_os = "linux"))]
 type RendererImpl = LazyVulkan;
 
-fn main() {
+fn main() -> anyhow::Result<()> {
     println!("Starting clipper!");
-    let (mut renderer, mut event_loop, mut gui, mut game, mut yak_winit) = init::<RendererImpl>();
+    let (mut renderer, mut event_loop, mut gui, mut game, mut yak_winit) = init::<RendererImpl>()?;
     let mut asset_loader = asset_loader::AssetLoader::new();
 
     // Off we go!
@@ -110,6 +110,7 @@ fn main() {
     });
 
     renderer.cleanup();
+    Ok(())
 }
 
 fn window_tick<R: Renderer>(
@@ -127,6 +128,7 @@ fn window_tick<R: Renderer>(
     if needs_restart {
         println!("Game needs restart!");
         game.resized(renderer.window().inner_size());
+        renderer.unload_assets();
     }
     renderer.update_assets(&mut game.world);
     renderer.render(
@@ -134,6 +136,6 @@ fn window_tick<R: Renderer>(
         &game.debug_lines,
         game.camera,
         &mut gui.yak,
-        1.,
+        game.calendar.daylight(),
     );
 }